@@ -0,0 +1,551 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::{input::InputSystem, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::storage::config_dir;
+
+const CONTROLS_FILE: &str = "controls.ron";
+
+// Plugins
+// Turns mouse, keyboard and gamepad buttons into game actions, the bindings
+// are read from `controls.ron` in the config directory.
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(InputMap::load_or_default())
+            .insert_resource(Actions::default())
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                read_actions
+                    .system()
+                    .label("read_actions")
+                    .after(InputSystem),
+            )
+            .add_system_to_stage(CoreStage::Last, save_input_map.system());
+    }
+}
+
+// What the player can do, whatever the device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum InputAction {
+    // Acting on the piece under the cursor
+    PointerGrab,
+    PointerRotate,
+    PointerFlip,
+    PointerFlipVertical,
+    // Acting on the focused piece
+    NextPiece,
+    PreviousPiece,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    Rotate,
+    Flip,
+    FlipVertical,
+    Place,
+    Cancel,
+    // The whole game
+    Hint,
+    Undo,
+    Redo,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputButton {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    // A button of any gamepad
+    Gamepad(GamepadButtonType),
+}
+
+// Buttons pressed together, the last one triggers the action while the others are held:
+// `[Key(LControl), Key(Z)]`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Binding(pub Vec<InputButton>);
+
+impl Binding {
+    fn trigger(&self) -> Option<InputButton> {
+        self.0.last().copied()
+    }
+
+    fn modifiers(&self) -> &[InputButton] {
+        &self.0[..self.0.len().saturating_sub(1)]
+    }
+}
+
+#[derive(Debug)]
+pub enum ControlsError {
+    Io(io::Error),
+    Parse(ron::Error),
+}
+
+impl fmt::Display for ControlsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ControlsError::Io(error) => write!(f, "cannot access the controls: {}", error),
+            ControlsError::Parse(error) => write!(f, "invalid controls: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ControlsError {}
+
+impl From<io::Error> for ControlsError {
+    fn from(error: io::Error) -> Self {
+        ControlsError::Io(error)
+    }
+}
+
+impl From<ron::Error> for ControlsError {
+    fn from(error: ron::Error) -> Self {
+        ControlsError::Parse(error)
+    }
+}
+
+// Resources
+// The buttons bound to each action
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputMap {
+    bindings: BTreeMap<InputAction, Vec<Binding>>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        use InputAction::*;
+        use InputButton::{Gamepad as Pad, Key, Mouse};
+
+        let both = |left: KeyCode, right: KeyCode, button: InputButton| {
+            vec![
+                Binding(vec![Key(left), button]),
+                Binding(vec![Key(right), button]),
+            ]
+        };
+        let shift = |button| both(KeyCode::LShift, KeyCode::RShift, button);
+        let control = |button| both(KeyCode::LControl, KeyCode::RControl, button);
+        let single = |buttons: &[InputButton]| -> Vec<Binding> {
+            buttons
+                .iter()
+                .map(|button| Binding(vec![*button]))
+                .collect()
+        };
+
+        let mut redo = control(Key(KeyCode::Y));
+        redo.push(Binding(vec![
+            Key(KeyCode::LControl),
+            Key(KeyCode::LShift),
+            Key(KeyCode::Z),
+        ]));
+        redo.push(Binding(vec![
+            Key(KeyCode::RControl),
+            Key(KeyCode::RShift),
+            Key(KeyCode::Z),
+        ]));
+        redo.push(Binding(vec![Pad(GamepadButtonType::RightTrigger2)]));
+        let mut undo = control(Key(KeyCode::Z));
+        undo.push(Binding(vec![Pad(GamepadButtonType::LeftTrigger2)]));
+        let mut previous = shift(Key(KeyCode::Tab));
+        previous.push(Binding(vec![Pad(GamepadButtonType::LeftTrigger)]));
+
+        let bindings = vec![
+            (PointerGrab, single(&[Mouse(MouseButton::Left)])),
+            (PointerRotate, single(&[Mouse(MouseButton::Right)])),
+            (PointerFlip, single(&[Mouse(MouseButton::Middle)])),
+            (PointerFlipVertical, shift(Mouse(MouseButton::Middle))),
+            (
+                NextPiece,
+                single(&[Key(KeyCode::Tab), Pad(GamepadButtonType::RightTrigger)]),
+            ),
+            (PreviousPiece, previous),
+            (
+                MoveLeft,
+                single(&[Key(KeyCode::Left), Pad(GamepadButtonType::DPadLeft)]),
+            ),
+            (
+                MoveRight,
+                single(&[Key(KeyCode::Right), Pad(GamepadButtonType::DPadRight)]),
+            ),
+            (
+                MoveUp,
+                single(&[Key(KeyCode::Up), Pad(GamepadButtonType::DPadUp)]),
+            ),
+            (
+                MoveDown,
+                single(&[Key(KeyCode::Down), Pad(GamepadButtonType::DPadDown)]),
+            ),
+            (
+                Rotate,
+                single(&[Key(KeyCode::R), Pad(GamepadButtonType::North)]),
+            ),
+            (
+                Flip,
+                single(&[Key(KeyCode::F), Pad(GamepadButtonType::West)]),
+            ),
+            (FlipVertical, shift(Key(KeyCode::F))),
+            (
+                Place,
+                single(&[
+                    Key(KeyCode::Return),
+                    Key(KeyCode::NumpadEnter),
+                    Pad(GamepadButtonType::South),
+                ]),
+            ),
+            (
+                Cancel,
+                single(&[Key(KeyCode::Escape), Pad(GamepadButtonType::East)]),
+            ),
+            (
+                Hint,
+                single(&[Key(KeyCode::H), Pad(GamepadButtonType::Select)]),
+            ),
+            (Undo, undo),
+            (Redo, redo),
+        ];
+        InputMap {
+            bindings: bindings.into_iter().collect(),
+        }
+    }
+}
+
+impl InputMap {
+    // The file in the config directory
+    pub fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join(CONTROLS_FILE))
+    }
+
+    // The saved controls, or the default ones written down for the player to edit
+    pub fn load_or_default() -> Self {
+        let path = match InputMap::path() {
+            Some(path) => path,
+            None => return InputMap::default(),
+        };
+        if path.exists() {
+            return InputMap::load(&path).unwrap_or_else(|error| {
+                warn!("{}: {}, default controls are used", path.display(), error);
+                InputMap::default()
+            });
+        }
+        let input_map = InputMap::default();
+        if let Err(error) = input_map.save(&path) {
+            warn!("{}: {}", path.display(), error);
+        }
+        input_map
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ControlsError> {
+        InputMap::from_ron(&fs::read_to_string(path)?)
+    }
+
+    // Actions missing from the file keep their default buttons
+    pub fn from_ron(content: &str) -> Result<Self, ControlsError> {
+        let loaded: InputMap = ron::de::from_str(content)?;
+        let mut input_map = InputMap::default();
+        input_map.bindings.extend(loaded.bindings);
+        Ok(input_map)
+    }
+
+    pub fn to_ron(&self) -> Result<String, ControlsError> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            // One line per action
+            ron::ser::PrettyConfig::new().with_depth_limit(2),
+        )?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ControlsError> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_ron()?)?;
+        Ok(())
+    }
+
+    pub fn bindings(&self, action: InputAction) -> &[Binding] {
+        self.bindings
+            .get(&action)
+            .map(|bindings| bindings.as_slice())
+            .unwrap_or(&[])
+    }
+
+    pub fn bind(&mut self, action: InputAction, binding: Binding) {
+        let bindings = self.bindings.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind(&mut self, action: InputAction, binding: &Binding) {
+        if let Some(bindings) = self.bindings.get_mut(&action) {
+            bindings.retain(|other| other != binding);
+        }
+    }
+
+    // Replaces every button of the action
+    pub fn rebind(&mut self, action: InputAction, bindings: Vec<Binding>) {
+        self.bindings.insert(action, bindings);
+    }
+}
+
+// The actions of the current frame, systems read them instead of the buttons
+#[derive(Debug, Clone, Default)]
+pub struct Actions {
+    pressed: HashSet<InputAction>,
+    just_pressed: HashSet<InputAction>,
+    just_released: HashSet<InputAction>,
+}
+
+impl Actions {
+    pub fn pressed(&self, action: InputAction) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: InputAction) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    pub fn just_released(&self, action: InputAction) -> bool {
+        self.just_released.contains(&action)
+    }
+    // Keeps a press from being handled twice, e.g. by a menu and then the game
+    pub fn consume(&mut self, action: InputAction) {
+        self.just_pressed.remove(&action);
+    }
+}
+
+// The buttons of every device
+#[derive(Debug, Default)]
+struct Buttons {
+    pressed: HashSet<InputButton>,
+    just_pressed: HashSet<InputButton>,
+    just_released: HashSet<InputButton>,
+}
+
+impl Buttons {
+    fn read(
+        keys: &Input<KeyCode>,
+        mouse: &Input<MouseButton>,
+        gamepads: &Input<GamepadButton>,
+    ) -> Self {
+        let mut buttons = Buttons::default();
+        buttons.pressed.extend(
+            keys.get_pressed()
+                .map(|key| InputButton::Key(*key))
+                .chain(
+                    mouse
+                        .get_pressed()
+                        .map(|button| InputButton::Mouse(*button)),
+                )
+                .chain(
+                    gamepads
+                        .get_pressed()
+                        .map(|button| InputButton::Gamepad(button.1)),
+                ),
+        );
+        buttons.just_pressed.extend(
+            keys.get_just_pressed()
+                .map(|key| InputButton::Key(*key))
+                .chain(
+                    mouse
+                        .get_just_pressed()
+                        .map(|button| InputButton::Mouse(*button)),
+                )
+                .chain(
+                    gamepads
+                        .get_just_pressed()
+                        .map(|button| InputButton::Gamepad(button.1)),
+                ),
+        );
+        buttons.just_released.extend(
+            keys.get_just_released()
+                .map(|key| InputButton::Key(*key))
+                .chain(
+                    mouse
+                        .get_just_released()
+                        .map(|button| InputButton::Mouse(*button)),
+                )
+                .chain(
+                    gamepads
+                        .get_just_released()
+                        .map(|button| InputButton::Gamepad(button.1)),
+                ),
+        );
+        buttons
+    }
+}
+
+impl Actions {
+    // The actions bound to the buttons
+    fn read(input_map: &InputMap, buttons: &Buttons) -> Self {
+        let triggered = |binding: &Binding| {
+            matches!(binding.trigger(), Some(trigger) if buttons.just_pressed.contains(&trigger))
+                && binding
+                    .modifiers()
+                    .iter()
+                    .all(|modifier| buttons.pressed.contains(modifier))
+        };
+
+        let mut actions = Actions::default();
+        let mut chords: Vec<(InputAction, &Binding)> = vec![];
+        for (action, bindings) in input_map.bindings.iter() {
+            for binding in bindings.iter() {
+                if !binding.0.is_empty()
+                    && binding
+                        .0
+                        .iter()
+                        .all(|button| buttons.pressed.contains(button))
+                {
+                    actions.pressed.insert(*action);
+                }
+                if matches!(binding.trigger(), Some(trigger) if buttons.just_released.contains(&trigger))
+                {
+                    actions.just_released.insert(*action);
+                }
+                if triggered(binding) {
+                    chords.push((*action, binding));
+                }
+            }
+        }
+
+        // Shift+Tab is not also a Tab: only the longest chords of a button count
+        for (action, binding) in chords.iter() {
+            let longest = chords
+                .iter()
+                .filter(|(_, other)| other.trigger() == binding.trigger())
+                .map(|(_, other)| other.0.len())
+                .max()
+                .unwrap_or(0);
+            if binding.0.len() == longest {
+                actions.just_pressed.insert(*action);
+            }
+        }
+        actions
+    }
+}
+
+// Systems
+fn read_actions(
+    input_map: Res<InputMap>,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    gamepads: Res<Input<GamepadButton>>,
+    mut actions: ResMut<Actions>,
+) {
+    *actions = Actions::read(&input_map, &Buttons::read(&keys, &mouse, &gamepads));
+}
+
+// Bindings changed while playing are kept for the next game
+fn save_input_map(input_map: Res<InputMap>) {
+    if !input_map.is_changed() || input_map.is_added() {
+        return;
+    }
+    if let Some(path) = InputMap::path() {
+        if let Err(error) = input_map.save(&path) {
+            warn!("{}: {}", path.display(), error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(keys: &[KeyCode]) -> HashSet<InputButton> {
+        keys.iter().map(|key| InputButton::Key(*key)).collect()
+    }
+
+    #[test]
+    fn missing_actions_keep_their_defaults() {
+        let input_map = InputMap::from_ron("(bindings: {Hint: [[Key(J)]]})").unwrap();
+        assert_eq!(
+            input_map.bindings(InputAction::Hint),
+            &[Binding(vec![InputButton::Key(KeyCode::J)])]
+        );
+        let defaults = InputMap::default();
+        assert_eq!(
+            input_map.bindings(InputAction::Undo),
+            defaults.bindings(InputAction::Undo)
+        );
+        assert!(matches!(
+            InputMap::from_ron("(bindings: {Jump: []})"),
+            Err(ControlsError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn saved_controls_are_loaded_back() {
+        let mut input_map = InputMap::default();
+        input_map.rebind(
+            InputAction::Rotate,
+            vec![Binding(vec![
+                InputButton::Key(KeyCode::LAlt),
+                InputButton::Mouse(MouseButton::Left),
+            ])],
+        );
+        input_map.unbind(
+            InputAction::Hint,
+            &Binding(vec![InputButton::Gamepad(GamepadButtonType::Select)]),
+        );
+        let path = std::env::temp_dir()
+            .join(format!("t-triste-controls-{}", std::process::id()))
+            .join(CONTROLS_FILE);
+        input_map.save(&path).unwrap();
+        let loaded = InputMap::load(&path);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert_eq!(loaded.unwrap(), input_map);
+    }
+
+    #[test]
+    fn longest_chord_wins() {
+        let input_map = InputMap::default();
+        let buttons = Buttons {
+            pressed: keys(&[KeyCode::LShift, KeyCode::Tab]),
+            just_pressed: keys(&[KeyCode::Tab]),
+            just_released: HashSet::new(),
+        };
+        let actions = Actions::read(&input_map, &buttons);
+        assert!(actions.just_pressed(InputAction::PreviousPiece));
+        assert!(!actions.just_pressed(InputAction::NextPiece));
+        // Both are held, only the press is taken by the chord
+        assert!(actions.pressed(InputAction::NextPiece));
+        assert!(actions.pressed(InputAction::PreviousPiece));
+
+        let buttons = Buttons {
+            pressed: keys(&[KeyCode::LControl, KeyCode::LShift, KeyCode::Z]),
+            just_pressed: keys(&[KeyCode::Z]),
+            just_released: HashSet::new(),
+        };
+        let actions = Actions::read(&input_map, &buttons);
+        assert!(actions.just_pressed(InputAction::Redo));
+        assert!(!actions.just_pressed(InputAction::Undo));
+    }
+
+    #[test]
+    fn modifiers_must_be_held() {
+        let input_map = InputMap::default();
+        let buttons = Buttons {
+            pressed: keys(&[KeyCode::Z]),
+            just_pressed: keys(&[KeyCode::Z]),
+            just_released: HashSet::new(),
+        };
+        let actions = Actions::read(&input_map, &buttons);
+        assert!(!actions.just_pressed(InputAction::Undo));
+
+        // Releasing the trigger ends every action it starts
+        let buttons = Buttons {
+            pressed: keys(&[KeyCode::LShift]),
+            just_pressed: HashSet::new(),
+            just_released: keys(&[KeyCode::Tab]),
+        };
+        let actions = Actions::read(&input_map, &buttons);
+        assert!(actions.just_released(InputAction::NextPiece));
+        assert!(actions.just_released(InputAction::PreviousPiece));
+        assert!(!actions.pressed(InputAction::NextPiece));
+    }
+}
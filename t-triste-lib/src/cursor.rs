@@ -1,5 +1,7 @@
 use bevy::prelude::*;
 
+use crate::controls::{Actions, InputAction};
+
 pub struct CursorPlugin;
 
 impl Plugin for CursorPlugin {
@@ -10,7 +12,10 @@ impl Plugin for CursorPlugin {
                 last_click_pos: Vec2::default(),
                 is_pressed: false
             })
-            .add_system_to_stage(CoreStage::PreUpdate, cursor_state.system());
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                cursor_state.system().after("read_actions"),
+            );
     }
 }
 
@@ -22,19 +27,20 @@ pub struct Cursor {
 
 fn cursor_state(
     mut cursor_moved_event: EventReader<CursorMoved>,
-    mouse_button_input: Res<Input<MouseButton>>,
+    actions: Res<Actions>,
     mut cursor: ResMut<Cursor>,
 ) {
     for event in cursor_moved_event.iter() {
         cursor.current_pos = event.position;
+    }
 
-        if mouse_button_input.just_pressed(MouseButton::Left) {
-            cursor.last_click_pos = event.position;
-            cursor.is_pressed = true;
-        }
+    // A click does not always come with a cursor move
+    if actions.just_pressed(InputAction::PointerGrab) {
+        cursor.last_click_pos = cursor.current_pos;
+        cursor.is_pressed = true;
+    }
 
-        if mouse_button_input.just_released(MouseButton::Left) {
-            cursor.is_pressed = false;
-        }
+    if actions.just_released(InputAction::PointerGrab) {
+        cursor.is_pressed = false;
     }
 }
@@ -0,0 +1,408 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt,
+    ops::RangeInclusive,
+};
+
+use crate::{
+    level::{BoardDescription, Difficulty, Level, PieceDescription},
+    piece::{
+        catalog::Polyomino, grid::GridPos, orientation::Orientation, polyomino::canonical,
+        PieceKind,
+    },
+    rating::rate,
+    solver::Solver,
+};
+
+// Cells fully shown in the 800x600 window
+const VISIBLE_COLS: RangeInclusive<i32> = 1..=15;
+const VISIBLE_ROWS: RangeInclusive<i32> = 1..=11;
+
+// Board cuts tried before giving up
+const MAX_ATTEMPTS: usize = 1000;
+
+// What the generated level looks like
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratorConfig {
+    pub width: usize,
+    pub height: usize,
+    // Squares of each piece, catalog pieces go up to 6
+    pub piece_sizes: RangeInclusive<usize>,
+    // Any number of pieces when there is none
+    pub piece_count: Option<usize>,
+    // Only keep boards that can be solved one way, turning the whole board aside
+    pub unique_solution: bool,
+    // Only keep boards rated this way, any rating when there is none
+    pub difficulty: Option<Difficulty>,
+    // The same seed always gives the same level
+    pub seed: u64,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        GeneratorConfig {
+            width: 5,
+            height: 3,
+            piece_sizes: 3..=5,
+            piece_count: None,
+            unique_solution: false,
+            difficulty: None,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GeneratorError {
+    // Pieces of these sizes cannot fill the board
+    InvalidSizes,
+    // The board does not fit in the window
+    BoardTooLarge,
+    // No room is left beside the board to lay the pieces out
+    NoRoomForPieces,
+    // No board cut gave a level matching the config
+    NoLevelFound,
+}
+
+impl fmt::Display for GeneratorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeneratorError::InvalidSizes => write!(f, "the pieces cannot fill the board"),
+            GeneratorError::BoardTooLarge => write!(
+                f,
+                "the board cannot be larger than {}x{}",
+                VISIBLE_COLS.end() - VISIBLE_COLS.start() + 1,
+                VISIBLE_ROWS.end() - VISIBLE_ROWS.start() + 1
+            ),
+            GeneratorError::NoRoomForPieces => {
+                write!(f, "the pieces do not fit beside the board")
+            }
+            GeneratorError::NoLevelFound => write!(f, "no level found, try another seed"),
+        }
+    }
+}
+
+impl std::error::Error for GeneratorError {}
+
+// Cuts a rectangle board into catalog polyominoes, the cut being a solution of the level.
+pub fn generate(config: &GeneratorConfig) -> Result<Level, GeneratorError> {
+    let sizes = config.piece_sizes.clone();
+    if config.width == 0
+        || config.height == 0
+        || *sizes.start() == 0
+        || *sizes.end() > 6
+        || sizes.is_empty()
+        || !can_fill(config.width * config.height, &sizes, config.piece_count)
+    {
+        return Err(GeneratorError::InvalidSizes);
+    }
+    if config.width as i32 > VISIBLE_COLS.end() - VISIBLE_COLS.start() + 1
+        || config.height as i32 > VISIBLE_ROWS.end() - VISIBLE_ROWS.start() + 1
+    {
+        return Err(GeneratorError::BoardTooLarge);
+    }
+
+    let mut rng = Rng::new(config.seed);
+    let board: Vec<GridPos> = (0..config.height as i32)
+        .flat_map(|row| (0..config.width as i32).map(move |col| GridPos::new(col, row)))
+        .collect();
+    for _ in 0..MAX_ATTEMPTS {
+        let piece_sizes = match random_sizes(board.len(), &sizes, config.piece_count, &mut rng) {
+            Some(piece_sizes) => piece_sizes,
+            None => continue,
+        };
+        let pieces = match cut(&board, &piece_sizes, &mut rng) {
+            Some(pieces) => pieces,
+            None => continue,
+        };
+        if config.unique_solution && !Solver::from_cells(&board, &pieces).has_unique_tiling() {
+            continue;
+        }
+        let difficulty = rate(&board, &pieces).difficulty();
+        if matches!(config.difficulty, Some(wanted) if wanted != difficulty) {
+            continue;
+        }
+        return level(config, &pieces, difficulty, &mut rng);
+    }
+    Err(GeneratorError::NoLevelFound)
+}
+
+// Whether some number of pieces of these sizes have `area` squares
+fn can_fill(area: usize, sizes: &RangeInclusive<usize>, piece_count: Option<usize>) -> bool {
+    let fits = |count: usize| count * sizes.start() <= area && area <= count * sizes.end();
+    match piece_count {
+        Some(count) => fits(count),
+        None => (1..=area).any(fits),
+    }
+}
+
+// Sizes of pieces filling `area` squares, none when the random picks lead to a dead end
+fn random_sizes(
+    area: usize,
+    sizes: &RangeInclusive<usize>,
+    piece_count: Option<usize>,
+    rng: &mut Rng,
+) -> Option<Vec<usize>> {
+    let (min, max) = (*sizes.start(), *sizes.end());
+    match piece_count {
+        Some(count) => {
+            if count * min > area || area > count * max {
+                return None;
+            }
+            let mut piece_sizes = vec![min; count];
+            let mut left = area - count * min;
+            while left > 0 {
+                let i = rng.below(count);
+                if piece_sizes[i] < max {
+                    piece_sizes[i] += 1;
+                    left -= 1;
+                }
+            }
+            Some(piece_sizes)
+        }
+        None => {
+            let mut piece_sizes = vec![];
+            let mut left = area;
+            while left > 0 {
+                // What is left must still make a piece
+                let candidates: Vec<usize> = (min..=max.min(left))
+                    .filter(|size| left == *size || left - size >= min)
+                    .collect();
+                if candidates.is_empty() {
+                    return None;
+                }
+                let size = candidates[rng.below(candidates.len())];
+                piece_sizes.push(size);
+                left -= size;
+            }
+            Some(piece_sizes)
+        }
+    }
+}
+
+// Grows pieces of the given sizes one after the other, from the most cornered free cell
+fn cut(board: &[GridPos], piece_sizes: &[usize], rng: &mut Rng) -> Option<Vec<Vec<GridPos>>> {
+    // Sorted sets keep the cut the same for a given seed
+    let mut free: BTreeSet<GridPos> = board.iter().copied().collect();
+    let mut piece_sizes = piece_sizes.to_vec();
+    piece_sizes.sort_unstable_by(|a, b| b.cmp(a));
+
+    let mut pieces = vec![];
+    for (i, size) in piece_sizes.iter().enumerate() {
+        let start = *free
+            .iter()
+            .min_by_key(|cell| neighbours(**cell).filter(|n| free.contains(n)).count())?;
+        free.remove(&start);
+        let mut piece = vec![start];
+        while piece.len() < *size {
+            let frontier: Vec<GridPos> = piece
+                .iter()
+                .flat_map(|cell| neighbours(*cell))
+                .filter(|cell| free.contains(cell))
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect();
+            if frontier.is_empty() {
+                return None;
+            }
+            let next = frontier[rng.below(frontier.len())];
+            free.remove(&next);
+            piece.push(next);
+        }
+        pieces.push(piece);
+
+        // A free area smaller than every piece left can never be filled
+        let smallest = piece_sizes[i + 1..].iter().min().copied().unwrap_or(0);
+        if areas(&free).iter().any(|area| *area < smallest) {
+            return None;
+        }
+    }
+    Some(pieces)
+}
+
+fn neighbours(cell: GridPos) -> impl Iterator<Item = GridPos> {
+    [(1, 0), (0, 1), (-1, 0), (0, -1)]
+        .iter()
+        .map(move |(col, row)| cell + GridPos::new(*col, *row))
+}
+
+// Sizes of the connected areas of the cells
+fn areas(cells: &BTreeSet<GridPos>) -> Vec<usize> {
+    let mut areas = vec![];
+    let mut seen = HashSet::new();
+    for cell in cells.iter() {
+        if !seen.insert(*cell) {
+            continue;
+        }
+        let mut area = vec![*cell];
+        let mut i = 0;
+        while i < area.len() {
+            for next in neighbours(area[i]) {
+                if cells.contains(&next) && seen.insert(next) {
+                    area.push(next);
+                }
+            }
+            i += 1;
+        }
+        areas.push(area.len());
+    }
+    areas
+}
+
+// The board on the right of the window, pieces scattered on its left
+fn level(
+    config: &GeneratorConfig,
+    pieces: &[Vec<GridPos>],
+    difficulty: Difficulty,
+    rng: &mut Rng,
+) -> Result<Level, GeneratorError> {
+    let (width, height) = (config.width as i32, config.height as i32);
+    let origin = GridPos::new(
+        (VISIBLE_COLS.end() + 1 - width).max(*VISIBLE_COLS.start()),
+        (VISIBLE_ROWS.start() + (VISIBLE_ROWS.end() - height) / 2).max(*VISIBLE_ROWS.start()),
+    );
+
+    // Pieces keep a free cell around them and around the board
+    let mut taken: HashSet<GridPos> = HashSet::new();
+    let board_cells: HashSet<GridPos> = (0..height)
+        .flat_map(|row| (0..width).map(move |col| origin + GridPos::new(col, row)))
+        .collect();
+    taken.extend(board_cells.iter().flat_map(|cell| around(*cell)));
+
+    let mut descriptions = vec![];
+    for piece in pieces.iter() {
+        let polyomino = Polyomino::of_order(piece.len())
+            .into_iter()
+            .find(|polyomino| canonical(&polyomino.cells()) == canonical(piece))
+            .unwrap();
+        let orientation = Orientation::all()[rng.below(8)];
+        let cells: Vec<GridPos> = polyomino
+            .cells()
+            .iter()
+            .map(|cell| orientation.apply(*cell))
+            .collect();
+        let position = free_position(&cells, &taken).ok_or(GeneratorError::NoRoomForPieces)?;
+        taken.extend(cells.iter().flat_map(|cell| around(*cell + position)));
+        descriptions.push(PieceDescription {
+            kind: PieceKind::Polyomino(polyomino),
+            position,
+            orientation,
+        });
+    }
+
+    Ok(Level {
+        name: format!(
+            "Generated {}x{} #{}",
+            config.width, config.height, config.seed
+        ),
+        difficulty,
+        board: BoardDescription {
+            origin,
+            shape: vec!["X".repeat(config.width); config.height],
+        },
+        pieces: descriptions,
+        par: None,
+    })
+}
+
+// The cell and the 8 cells around it
+fn around(cell: GridPos) -> impl Iterator<Item = GridPos> {
+    (-1..=1).flat_map(move |col| (-1..=1).map(move |row| cell + GridPos::new(col, row)))
+}
+
+// The first position, column by column, where the cells are visible and not taken
+fn free_position(cells: &[GridPos], taken: &HashSet<GridPos>) -> Option<GridPos> {
+    VISIBLE_COLS
+        .flat_map(|col| VISIBLE_ROWS.map(move |row| GridPos::new(col, row)))
+        .find(|position| {
+            cells.iter().all(|cell| {
+                let cell = *cell + *position;
+                VISIBLE_COLS.contains(&cell.col)
+                    && VISIBLE_ROWS.contains(&cell.row)
+                    && !taken.contains(&cell)
+            })
+        })
+}
+
+// SplitMix64, small and good enough to cut boards
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // In 0..n
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_pieces_are_visible_and_apart() {
+        for seed in 0..10 {
+            let config = GeneratorConfig {
+                width: 6,
+                height: 4,
+                seed,
+                ..Default::default()
+            };
+            let level = generate(&config).unwrap();
+            assert_eq!(level, generate(&config).unwrap());
+            let board = level.board();
+            let mut cells = HashSet::new();
+            for piece in level.pieces() {
+                for cell in piece.positions() {
+                    assert!(VISIBLE_COLS.contains(&cell.col) && VISIBLE_ROWS.contains(&cell.row));
+                    assert!(!board.contains(&cell));
+                    assert!(cells.insert(cell), "pieces overlap on {:?}", cell);
+                }
+            }
+            assert_eq!(cells.len(), 24);
+        }
+    }
+
+    #[test]
+    fn boards_larger_than_the_window_are_refused() {
+        let config = |width, height| GeneratorConfig {
+            width,
+            height,
+            ..Default::default()
+        };
+        assert_eq!(generate(&config(16, 3)), Err(GeneratorError::BoardTooLarge));
+        assert_eq!(generate(&config(5, 12)), Err(GeneratorError::BoardTooLarge));
+        assert_eq!(
+            generate(&GeneratorConfig {
+                piece_sizes: 2..=2,
+                ..config(3, 3)
+            }),
+            Err(GeneratorError::InvalidSizes)
+        );
+    }
+
+    #[test]
+    fn pieces_need_room_beside_the_board() {
+        let config = GeneratorConfig {
+            width: 15,
+            height: 11,
+            ..Default::default()
+        };
+        let pieces = vec![vec![GridPos::default()]];
+        assert_eq!(
+            level(&config, &pieces, Difficulty::Easy, &mut Rng::new(0)),
+            Err(GeneratorError::NoRoomForPieces)
+        );
+    }
+}
@@ -0,0 +1,286 @@
+use bevy::prelude::*;
+
+use crate::{
+    controls::{Actions, InputAction},
+    piece::{
+        board::Board, grid::GridPos, world::WorldPos, GridTransform, PieceColor, PieceId,
+        PieceMaterials, PieceShape, SQUARE_WIDTH,
+    },
+    score::Score,
+    solver::Solver,
+    state::{despawn_all, AppState, GameMode},
+};
+
+// Plugins
+pub struct HintPlugin;
+
+impl Plugin for HintPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(CurrentHint(None))
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(clear_hint.system().label("clear_hint"))
+                    .with_system(ask_hint.system().label("ask_hint").after("clear_hint"))
+                    .with_system(draw_hint.system().after("ask_hint")),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Playing)
+                    .with_system(forget_hint.system())
+                    .with_system(despawn_all::<HintCell>.system()),
+            );
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Hint {
+    // Putting this piece on these cells still leads to a solution
+    Place { piece: PieceId, cells: Vec<GridPos> },
+    // This piece is on the board but no solution goes through it
+    Misplaced { piece: PieceId },
+    // The pieces on the board block the puzzle all together
+    Restart,
+    // The level cannot be solved with its pieces
+    Unsolvable,
+    // Every piece is already on the board
+    Solved,
+}
+
+// Components
+// Marker component for the highlighted cells
+struct HintCell;
+
+// Resources
+pub struct CurrentHint(pub Option<Hint>);
+
+// Find a hint from the pieces already on the board
+pub fn find_hint(board: &Board, pieces: &[(PieceId, Vec<GridPos>)]) -> Hint {
+    // The solver knows pieces by their index in the list
+    let shapes: Vec<Vec<GridPos>> = pieces.iter().map(|(_, cells)| cells.clone()).collect();
+    let placed: Vec<usize> = (0..pieces.len())
+        .filter(|piece| board.is_placed(pieces[*piece].0))
+        .collect();
+    if placed.len() == pieces.len() {
+        return Hint::Solved;
+    }
+
+    let solver_without = |ignored: Option<usize>| {
+        let mut solver = Solver::new(board, &shapes);
+        for piece in placed.iter().filter(|piece| Some(**piece) != ignored) {
+            solver.fix(*piece, &shapes[*piece]);
+        }
+        solver
+    };
+
+    if let Some(solution) = solver_without(None).first_solution() {
+        // Hint the first piece that is not on the board yet
+        let placement = solution
+            .into_iter()
+            .find(|placement| !placed.contains(&placement.piece))
+            .unwrap();
+        return Hint::Place {
+            piece: pieces[placement.piece].0,
+            cells: placement.cells,
+        };
+    }
+
+    // Look for the piece that blocks the others
+    for piece in placed.iter() {
+        if solver_without(Some(*piece)).first_solution().is_some() {
+            return Hint::Misplaced {
+                piece: pieces[*piece].0,
+            };
+        }
+    }
+    if Solver::new(board, &shapes).first_solution().is_some() {
+        Hint::Restart
+    } else {
+        Hint::Unsolvable
+    }
+}
+
+// Systems
+fn ask_hint(
+    actions: Res<Actions>,
+    mode: Res<GameMode>,
+    board: Res<Board>,
+    mut current_hint: ResMut<CurrentHint>,
+    mut score: ResMut<Score>,
+    query: Query<(&PieceId, &PieceShape, &GridTransform)>,
+) {
+    // No help in a race
+    if *mode == GameMode::Versus || !actions.just_pressed(InputAction::Hint) {
+        return;
+    }
+
+    let mut pieces: Vec<(PieceId, Vec<GridPos>)> = query
+        .iter()
+        .map(|(id, shape, grid_transform)| (*id, grid_transform.positions(shape)))
+        .collect();
+    // Hints do not depend on the order of the query
+    pieces.sort_by_key(|(id, _)| id.0);
+    let hint = find_hint(&board, &pieces);
+    // Asking again for the hint on screen is free
+    if current_hint.0.as_ref() == Some(&hint) {
+        return;
+    }
+    match hint {
+        Hint::Misplaced { piece } => warn!("Piece {} cannot be part of a solution", piece.0),
+        Hint::Restart => warn!("The pieces on the board cannot lead to a solution"),
+        Hint::Unsolvable => warn!("This level has no solution"),
+        _ => {}
+    }
+    // Only a hint telling what to do costs a star
+    if matches!(hint, Hint::Place { .. } | Hint::Misplaced { .. }) {
+        score.hints += 1;
+    }
+    current_hint.0 = Some(hint);
+}
+
+// Any change on the board makes the hint outdated
+fn clear_hint(board: Res<Board>, mut current_hint: ResMut<CurrentHint>) {
+    if board.is_changed() && current_hint.0.is_some() {
+        current_hint.0 = None;
+    }
+}
+
+fn forget_hint(mut current_hint: ResMut<CurrentHint>) {
+    current_hint.0 = None;
+}
+
+fn draw_hint(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut piece_materials: ResMut<PieceMaterials>,
+    current_hint: Res<CurrentHint>,
+    query: Query<Entity, With<HintCell>>,
+    pieces: Query<(&PieceId, &PieceShape, &GridTransform, &PieceColor)>,
+) {
+    if !current_hint.is_changed() {
+        return;
+    }
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let find_piece = |id: PieceId| pieces.iter().find(|(piece, ..)| **piece == id);
+    let (cells, color, size, z_index) = match &current_hint.0 {
+        // Show where the piece goes, under the pieces
+        Some(Hint::Place { piece, cells }) => match find_piece(*piece) {
+            Some((_, _, _, color)) => (cells.clone(), color.0, SQUARE_WIDTH - 1, 0.5),
+            None => return,
+        },
+        // Mark the wrong piece, over the pieces
+        Some(Hint::Misplaced { piece }) => match find_piece(*piece) {
+            Some((_, shape, grid_transform, _)) => (
+                grid_transform.positions(shape),
+                Color::rgb(0.9, 0., 0.),
+                SQUARE_WIDTH / 3,
+                2.,
+            ),
+            None => return,
+        },
+        _ => return,
+    };
+
+    let material = piece_materials.get(&mut materials, color);
+    for cell in cells {
+        commands
+            .spawn_bundle(SpriteBundle {
+                material: material.clone(),
+                sprite: Sprite::new(Vec2::new(size as f32, size as f32)),
+                transform: Transform::from_translation(cell.to_world(z_index)),
+                ..Default::default()
+            })
+            .insert(HintCell);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piece::board::PlacementResult;
+
+    fn line(length: i32) -> Board {
+        Board::new(
+            GridPos::default(),
+            (0..length).map(|col| GridPos::new(col, 0)).collect(),
+        )
+    }
+
+    fn domino(col: i32, row: i32) -> Vec<GridPos> {
+        vec![GridPos::new(col, row), GridPos::new(col + 1, row)]
+    }
+
+    fn place(board: &mut Board, (id, cells): &(PieceId, Vec<GridPos>)) {
+        assert!(matches!(
+            board.place(*id, cells),
+            PlacementResult::Placed { .. }
+        ));
+    }
+
+    #[test]
+    fn place_a_piece_not_on_the_board() {
+        // Ids do not have to follow each other
+        let pieces = vec![(PieceId(4), domino(5, 5)), (PieceId(9), domino(5, 7))];
+        let mut board = line(4);
+        match find_hint(&board, &pieces) {
+            Hint::Place { piece, cells } => {
+                assert_eq!(piece, PieceId(4));
+                assert!(cells.iter().all(|cell| board.contains(cell)));
+            }
+            hint => panic!("Unexpected hint {:?}", hint),
+        }
+        // The piece already on the board is not hinted again
+        let placed = (PieceId(4), domino(0, 0));
+        place(&mut board, &placed);
+        let pieces = vec![placed, (PieceId(9), domino(5, 7))];
+        assert_eq!(
+            find_hint(&board, &pieces),
+            Hint::Place {
+                piece: PieceId(9),
+                cells: domino(2, 0)
+            }
+        );
+    }
+
+    #[test]
+    fn misplaced_piece_splits_the_board() {
+        let mut board = line(4);
+        let placed = (PieceId(1), domino(1, 0));
+        place(&mut board, &placed);
+        let pieces = vec![(PieceId(0), domino(5, 5)), placed];
+        assert_eq!(
+            find_hint(&board, &pieces),
+            Hint::Misplaced { piece: PieceId(1) }
+        );
+    }
+
+    #[test]
+    fn restart_when_the_pieces_block_together() {
+        let mut board = line(6);
+        let first = (PieceId(0), domino(1, 0));
+        let second = (PieceId(1), domino(3, 0));
+        place(&mut board, &first);
+        place(&mut board, &second);
+        // Taking either piece away still leaves a single cell alone
+        let pieces = vec![first, second, (PieceId(2), domino(5, 5))];
+        assert_eq!(find_hint(&board, &pieces), Hint::Restart);
+    }
+
+    #[test]
+    fn unsolvable_level() {
+        let board = line(3);
+        let pieces = vec![(PieceId(0), domino(5, 5)), (PieceId(1), domino(5, 7))];
+        assert_eq!(find_hint(&board, &pieces), Hint::Unsolvable);
+    }
+
+    #[test]
+    fn solved_when_every_piece_is_placed() {
+        let mut board = line(4);
+        let pieces = vec![(PieceId(0), domino(0, 0)), (PieceId(1), domino(2, 0))];
+        for piece in pieces.iter() {
+            place(&mut board, piece);
+        }
+        assert_eq!(find_hint(&board, &pieces), Hint::Solved);
+    }
+}
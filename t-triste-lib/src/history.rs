@@ -0,0 +1,381 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    controls::{Actions, InputAction},
+    piece::{
+        board::{Board, PlacementResult},
+        keyboard::Lifted,
+        Dragging, GridTransform, PieceId, PieceShape, Placed,
+    },
+    state::{AppState, GameMode},
+};
+
+// Plugins
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(History::default())
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing).with_system(clear_history.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing).with_system(undo_redo.system()),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ActionKind {
+    // A piece beside the board is grabbed
+    Pick,
+    // A piece changed cell while dragged, or went back where it was picked up
+    Move,
+    Rotate,
+    Flip,
+    // A dropped piece snapped on the board
+    Place,
+    // A piece on the board is grabbed, it leaves the board
+    Remove,
+}
+
+// Where a piece is and whether the board holds it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PieceState {
+    pub grid_transform: GridTransform,
+    pub placed: bool,
+}
+
+impl PieceState {
+    pub fn new(grid_transform: GridTransform, placed: bool) -> Self {
+        PieceState {
+            grid_transform,
+            placed,
+        }
+    }
+}
+
+// A change of a piece, undone by going back to the state before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Action {
+    pub kind: ActionKind,
+    pub piece: PieceId,
+    pub before: PieceState,
+    pub after: PieceState,
+}
+
+// Resources
+// Actions grouped in steps, a step being what a single undo reverts:
+// everything from picking a piece up to dropping it is one step.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct History {
+    steps: Vec<Vec<Action>>,
+    // Steps before this one are done, the others can be redone
+    done: usize,
+}
+
+impl History {
+    // Starts a new step, the undone steps cannot be redone anymore
+    pub fn record(&mut self, action: Action) {
+        self.steps.truncate(self.done);
+        self.steps.push(vec![action]);
+        self.done = self.steps.len();
+    }
+
+    // Adds the action to the last step, a new step is started if it was undone
+    pub fn join(&mut self, action: Action) {
+        if self.done == 0 || self.done < self.steps.len() {
+            self.record(action);
+            return;
+        }
+        let step = &mut self.steps[self.done - 1];
+        // Cells crossed by a dragged piece are a single move
+        match step.last_mut() {
+            Some(last)
+                if last.kind == ActionKind::Move
+                    && action.kind == ActionKind::Move
+                    && last.piece == action.piece =>
+            {
+                last.after = action.after
+            }
+            _ => step.push(action),
+        }
+    }
+
+    // Forgets the last step if it left its pieces as they were, like a piece
+    // picked up and dropped back in place
+    pub fn drop_unchanged_step(&mut self) {
+        if self.done == 0 || self.done < self.steps.len() {
+            return;
+        }
+        let step = &self.steps[self.done - 1];
+        let unchanged = step.iter().all(|action| {
+            let first = step.iter().find(|a| a.piece == action.piece).unwrap();
+            let last = step.iter().rev().find(|a| a.piece == action.piece).unwrap();
+            first.before == last.after
+        });
+        if unchanged {
+            self.steps.pop();
+            self.done -= 1;
+        }
+    }
+
+    // The actions of the last done step, to revert from the last one
+    pub fn undo(&mut self) -> Option<Vec<Action>> {
+        if self.done == 0 {
+            return None;
+        }
+        self.done -= 1;
+        Some(self.steps[self.done].iter().rev().copied().collect())
+    }
+
+    // The actions of the first undone step, to apply from the first one
+    pub fn redo(&mut self) -> Option<Vec<Action>> {
+        if self.done == self.steps.len() {
+            return None;
+        }
+        self.done += 1;
+        Some(self.steps[self.done - 1].clone())
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.done > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.done < self.steps.len()
+    }
+
+    // Done steps
+    pub fn len(&self) -> usize {
+        self.done
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.done == 0
+    }
+
+    // Forgets every step after the first `len` ones, undone steps included
+    pub fn truncate(&mut self, len: usize) {
+        self.steps.truncate(len);
+        self.done = self.done.min(len);
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    // The done actions in order, applying their `after` state to the pieces
+    // of a fresh level gets back to the current game
+    pub fn replay(&self) -> impl Iterator<Item = &Action> {
+        self.steps[..self.done].iter().flatten()
+    }
+}
+
+// Systems
+#[allow(clippy::type_complexity)]
+fn undo_redo(
+    mut commands: Commands,
+    actions: Res<Actions>,
+    mode: Res<GameMode>,
+    mut history: ResMut<History>,
+    mut board: ResMut<Board>,
+    mut pieces: Query<(Entity, &PieceId, &PieceShape, &mut GridTransform), Without<Dragging>>,
+    moving: Query<Entity, Or<(With<Dragging>, With<Lifted>)>>,
+) {
+    // The history is left alone while a piece is moved, and in a race where
+    // both players would share it
+    if *mode == GameMode::Versus || moving.iter().next().is_some() {
+        return;
+    }
+
+    let states: Vec<(PieceId, PieceState)> = if actions.just_pressed(InputAction::Undo) {
+        match history.undo() {
+            Some(step) => step.iter().map(|a| (a.piece, a.before)).collect(),
+            None => return,
+        }
+    } else if actions.just_pressed(InputAction::Redo) {
+        match history.redo() {
+            Some(step) => step.iter().map(|a| (a.piece, a.after)).collect(),
+            None => return,
+        }
+    } else {
+        return;
+    };
+
+    for (piece, state) in states {
+        for (entity, id, shape, mut grid_transform) in pieces.iter_mut() {
+            if *id == piece {
+                set_state(
+                    &mut commands,
+                    &mut board,
+                    entity,
+                    *id,
+                    shape,
+                    &mut grid_transform,
+                    state,
+                );
+            }
+        }
+    }
+}
+
+// Each level starts with an empty history
+fn clear_history(mut history: ResMut<History>) {
+    history.clear();
+}
+
+// Puts the piece back in a recorded state
+pub(crate) fn set_state(
+    commands: &mut Commands,
+    board: &mut Board,
+    entity: Entity,
+    id: PieceId,
+    shape: &PieceShape,
+    grid_transform: &mut GridTransform,
+    state: PieceState,
+) {
+    *grid_transform = state.grid_transform;
+    board.remove(id);
+    let placed = state.placed
+        && match board.place(id, &grid_transform.positions(shape)) {
+            PlacementResult::Placed { offset } => {
+                grid_transform.translate(offset);
+                true
+            }
+            _ => false,
+        };
+    if placed {
+        commands.entity(entity).insert(Placed);
+    } else {
+        commands.entity(entity).remove::<Placed>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piece::grid::GridPos;
+
+    fn state(col: i32, placed: bool) -> PieceState {
+        PieceState::new(GridTransform::new(GridPos::new(col, 0)), placed)
+    }
+
+    fn action(kind: ActionKind, piece: usize, from: i32, to: i32) -> Action {
+        Action {
+            kind,
+            piece: PieceId(piece),
+            before: state(from, false),
+            after: state(to, false),
+        }
+    }
+
+    #[test]
+    fn join_merges_the_moves_of_a_piece() {
+        let mut history = History::default();
+        history.record(action(ActionKind::Pick, 0, 0, 0));
+        history.join(action(ActionKind::Move, 0, 0, 1));
+        history.join(action(ActionKind::Move, 0, 1, 2));
+        // Another piece or another kind starts a new action in the step
+        history.join(action(ActionKind::Move, 1, 5, 6));
+        history.join(action(ActionKind::Rotate, 1, 6, 6));
+        history.join(action(ActionKind::Move, 1, 6, 7));
+        assert_eq!(history.len(), 1);
+        let step = history.undo().unwrap();
+        assert_eq!(
+            step,
+            vec![
+                action(ActionKind::Move, 1, 6, 7),
+                action(ActionKind::Rotate, 1, 6, 6),
+                action(ActionKind::Move, 1, 5, 6),
+                action(ActionKind::Move, 0, 0, 2),
+                action(ActionKind::Pick, 0, 0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn join_after_an_undo_starts_a_step() {
+        let mut history = History::default();
+        history.join(action(ActionKind::Move, 0, 0, 1));
+        history.undo();
+        history.join(action(ActionKind::Move, 0, 0, 2));
+        assert_eq!(history.len(), 1);
+        assert!(!history.can_redo());
+        assert_eq!(history.replay().count(), 1);
+    }
+
+    #[test]
+    fn record_clears_redo() {
+        let mut history = History::default();
+        history.record(action(ActionKind::Rotate, 0, 0, 0));
+        history.record(action(ActionKind::Flip, 0, 0, 0));
+        assert!(history.undo().is_some());
+        assert!(history.can_redo());
+        history.record(action(ActionKind::Pick, 1, 3, 3));
+        assert!(!history.can_redo());
+        assert_eq!(history.redo(), None);
+        let kinds: Vec<ActionKind> = history.replay().map(|action| action.kind).collect();
+        assert_eq!(kinds, vec![ActionKind::Rotate, ActionKind::Pick]);
+    }
+
+    #[test]
+    fn undo_and_redo_walk_the_steps() {
+        let mut history = History::default();
+        assert_eq!(history.undo(), None);
+        history.record(action(ActionKind::Pick, 0, 0, 0));
+        history.join(action(ActionKind::Move, 0, 0, 3));
+        assert_eq!(
+            history.undo(),
+            Some(vec![
+                action(ActionKind::Move, 0, 0, 3),
+                action(ActionKind::Pick, 0, 0, 0),
+            ])
+        );
+        assert!(history.is_empty());
+        assert_eq!(
+            history.redo(),
+            Some(vec![
+                action(ActionKind::Pick, 0, 0, 0),
+                action(ActionKind::Move, 0, 0, 3),
+            ])
+        );
+        assert_eq!(history.redo(), None);
+    }
+
+    #[test]
+    fn drop_unchanged_step() {
+        let mut history = History::default();
+        history.record(action(ActionKind::Pick, 0, 0, 0));
+        history.join(action(ActionKind::Move, 0, 0, 2));
+        history.join(action(ActionKind::Move, 0, 2, 0));
+        history.drop_unchanged_step();
+        assert!(history.is_empty());
+        history.record(action(ActionKind::Pick, 0, 0, 0));
+        history.join(action(ActionKind::Move, 0, 0, 2));
+        history.drop_unchanged_step();
+        assert_eq!(history.len(), 1);
+    }
+
+    #[test]
+    fn truncate_clamps_done() {
+        let mut history = History::default();
+        for col in 0..3 {
+            history.record(action(ActionKind::Rotate, 0, col, col));
+        }
+        history.undo();
+        assert_eq!(history.len(), 2);
+        // Undone steps are cut first
+        history.truncate(2);
+        assert_eq!(history.len(), 2);
+        assert!(!history.can_redo());
+        history.truncate(1);
+        assert_eq!(history.len(), 1);
+        history.truncate(5);
+        assert_eq!(history.len(), 1);
+        history.clear();
+        assert!(history.is_empty());
+        assert!(!history.can_undo());
+    }
+}
@@ -0,0 +1,342 @@
+use std::{fmt, fs, io, path::Path};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    piece::{
+        board::Board,
+        grid::{parse_cells, GridPos},
+        orientation::Orientation,
+        spawn_piece, PieceBundle, PieceId, PieceKind, PieceMaterials,
+    },
+    state::{despawn_all, AppState},
+};
+
+// Levels shipped with the game, in play order.
+// A new level file, like one written by the `generate` bin, must be added here.
+pub const BUILTIN_LEVELS: &[&str] = &[include_str!("../levels/01_first_steps.ron")];
+
+// Plugins
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let pack = LevelPack::builtin();
+        let level = pack.levels[0].clone();
+        // The played level and its board are replaced when a level starts
+        app.insert_resource(level.board())
+            .insert_resource(level)
+            .insert_resource(pack)
+            .insert_resource(CurrentLevel(0))
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing)
+                    .with_system(spawn_level.system().label("spawn_level")),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Playing).with_system(despawn_all::<PieceId>.system()),
+            );
+    }
+}
+
+// A level, as described in a RON file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Level {
+    pub name: String,
+    pub difficulty: Difficulty,
+    pub board: BoardDescription,
+    pub pieces: Vec<PieceDescription>,
+    // Guessed from the pieces when missing, see `Level::par`
+    #[serde(default)]
+    pub par: Option<Par>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BoardDescription {
+    // Bottom left cell of the board
+    pub origin: GridPos,
+    // Top row first, `X` is a cell of the board and `.` a hole
+    pub shape: Vec<String>,
+}
+
+// What a good player does, the score earns stars against it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Par {
+    // In seconds
+    pub time: f64,
+    pub moves: u32,
+}
+
+// Par of a level without one: each piece is placed and turned once
+const PAR_MOVES_PER_PIECE: u32 = 2;
+const PAR_TIME_PER_PIECE: f64 = 20.;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PieceDescription {
+    pub kind: PieceKind,
+    pub position: GridPos,
+    #[serde(default)]
+    pub orientation: Orientation,
+}
+
+// Resources
+// The levels that can be played, in order
+pub struct LevelPack {
+    // Known by the player profile, it must not change between versions
+    pub name: String,
+    pub levels: Vec<Level>,
+}
+
+impl LevelPack {
+    pub fn builtin() -> Self {
+        LevelPack {
+            name: "T-Triste".to_string(),
+            levels: BUILTIN_LEVELS
+                .iter()
+                .map(|level| Level::from_ron(level).expect("A builtin level is invalid"))
+                .collect(),
+        }
+    }
+}
+
+// Index in the pack of the level being played
+pub struct CurrentLevel(pub usize);
+
+#[derive(Debug)]
+pub enum LevelError {
+    Io(io::Error),
+    Parse(ron::Error),
+    UnknownCell(char),
+    EmptyBoard,
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelError::Io(error) => write!(f, "cannot read the level: {}", error),
+            LevelError::Parse(error) => write!(f, "invalid level: {}", error),
+            LevelError::UnknownCell(c) => write!(f, "unknown board cell '{}'", c),
+            LevelError::EmptyBoard => write!(f, "the board has no cell"),
+        }
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+impl From<io::Error> for LevelError {
+    fn from(error: io::Error) -> Self {
+        LevelError::Io(error)
+    }
+}
+
+impl From<ron::Error> for LevelError {
+    fn from(error: ron::Error) -> Self {
+        LevelError::Parse(error)
+    }
+}
+
+impl Level {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, LevelError> {
+        Level::from_ron(&fs::read_to_string(path)?)
+    }
+
+    pub fn from_ron(content: &str) -> Result<Self, LevelError> {
+        let level: Level = ron::de::from_str(content)?;
+        // Fail early on a bad board rather than when the level is played
+        level.board.cells()?;
+        Ok(level)
+    }
+
+    pub fn to_ron(&self) -> Result<String, LevelError> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::new(),
+        )?)
+    }
+
+    pub fn par(&self) -> Par {
+        self.par.unwrap_or_else(|| {
+            let pieces = self.pieces.len();
+            Par {
+                time: PAR_TIME_PER_PIECE * pieces as f64,
+                moves: PAR_MOVES_PER_PIECE * pieces as u32,
+            }
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), LevelError> {
+        fs::write(path, self.to_ron()?)?;
+        Ok(())
+    }
+
+    pub fn board(&self) -> Board {
+        // The shape was checked when the level was loaded
+        Board::new(self.board.origin, self.board.cells().unwrap())
+    }
+
+    pub fn pieces(&self) -> Vec<PieceBundle> {
+        self.pieces
+            .iter()
+            .enumerate()
+            .map(|(i, description)| {
+                let mut bundle = description
+                    .kind
+                    .build(description.position)
+                    .bundle(PieceId(i));
+                // Turning around its start position keeps the piece where it was described
+                bundle
+                    .grid_transform
+                    .reorient(description.orientation, description.position);
+                bundle
+            })
+            .collect()
+    }
+}
+
+// Systems
+fn spawn_level(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut piece_materials: ResMut<PieceMaterials>,
+    pack: Res<LevelPack>,
+    current: Res<CurrentLevel>,
+    mut level: ResMut<Level>,
+    mut board: ResMut<Board>,
+) {
+    // Resources are changed right away, so the board is drawn from the new level
+    *level = pack.levels[current.0].clone();
+    *board = level.board();
+    for bundle in level.pieces() {
+        let material = piece_materials.get(&mut materials, bundle.color.0);
+        spawn_piece(&mut commands, material, bundle);
+    }
+}
+
+impl BoardDescription {
+    // Cells relative to the origin, the last row of the shape being row 0
+    pub fn cells(&self) -> Result<Vec<GridPos>, LevelError> {
+        let cells =
+            parse_cells(self.shape.iter().map(String::as_str)).map_err(LevelError::UnknownCell)?;
+        if cells.is_empty() {
+            return Err(LevelError::EmptyBoard);
+        }
+        Ok(cells)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piece::orientation::Rotation;
+
+    fn level(board: &str, pieces: &str) -> Result<Level, LevelError> {
+        Level::from_ron(&format!(
+            "(name: \"Test\", difficulty: Easy, board: {}, pieces: [{}])",
+            board, pieces
+        ))
+    }
+
+    #[test]
+    fn builtin_level_is_read() {
+        let level = Level::from_ron(BUILTIN_LEVELS[0]).unwrap();
+        assert_eq!(level.name, "First steps");
+        assert_eq!(level.difficulty, Difficulty::Easy);
+        assert_eq!(level.board.origin, GridPos::new(6, 5));
+        assert_eq!(level.board.cells().unwrap().len(), 15);
+        assert_eq!(level.pieces.len(), 5);
+        assert_eq!(level.pieces[1].kind, PieceKind::L);
+        assert_eq!(
+            level.par(),
+            Par {
+                time: 60.,
+                moves: 8
+            }
+        );
+        // What is saved is read back the same
+        assert_eq!(Level::from_ron(&level.to_ron().unwrap()).unwrap(), level);
+    }
+
+    #[test]
+    fn board_holes_are_not_cells() {
+        let board = BoardDescription {
+            origin: GridPos::new(3, 4),
+            shape: vec!["X.X".to_string(), "XXX".to_string()],
+        };
+        assert_eq!(
+            board.cells().unwrap(),
+            vec![
+                GridPos::new(0, 0),
+                GridPos::new(1, 0),
+                GridPos::new(2, 0),
+                GridPos::new(0, 1),
+                GridPos::new(2, 1),
+            ]
+        );
+        // The board puts the cells at its origin
+        let level = level("(origin: (col: 3, row: 4), shape: [\"X.X\", \"XXX\"])", "").unwrap();
+        let board = level.board();
+        assert!(board.contains(&GridPos::new(3, 5)));
+        assert!(!board.contains(&GridPos::new(4, 5)));
+    }
+
+    #[test]
+    fn bad_boards_are_rejected() {
+        let unknown = level("(origin: (col: 0, row: 0), shape: [\"XO\"])", "");
+        assert!(matches!(unknown, Err(LevelError::UnknownCell('O'))));
+        let empty = level("(origin: (col: 0, row: 0), shape: [\"..\", \"\"])", "");
+        assert!(matches!(empty, Err(LevelError::EmptyBoard)));
+        let parse = level("(origin: (col: 0, row: 0))", "");
+        assert!(matches!(parse, Err(LevelError::Parse(_))));
+    }
+
+    #[test]
+    fn pieces_are_turned_around_their_position() {
+        let level = level(
+            "(origin: (col: 0, row: 0), shape: [\"X\"])",
+            "(kind: Rectangle, position: (col: 5, row: 5)),
+             (kind: Rectangle, position: (col: 9, row: 2), orientation: (rotation: R90))",
+        )
+        .unwrap();
+        let pieces = level.pieces();
+        assert_eq!(pieces[0].id, PieceId(0));
+        assert_eq!(pieces[1].id, PieceId(1));
+        assert_eq!(pieces[0].grid_transform.orientation, Orientation::default());
+        assert_eq!(
+            pieces[0].positions(),
+            vec![GridPos::new(5, 5), GridPos::new(5, 6), GridPos::new(5, 7)]
+        );
+        assert_eq!(
+            pieces[1].grid_transform.orientation,
+            Orientation::new(Rotation::R90, false)
+        );
+        assert_eq!(
+            pieces[1].positions(),
+            vec![GridPos::new(9, 2), GridPos::new(8, 2), GridPos::new(7, 2)]
+        );
+    }
+
+    #[test]
+    fn missing_par_depends_on_the_pieces() {
+        let level = level(
+            "(origin: (col: 0, row: 0), shape: [\"XXX\"])",
+            "(kind: Rectangle, position: (col: 0, row: 0)),
+             (kind: Square, position: (col: 0, row: 0))",
+        )
+        .unwrap();
+        assert_eq!(level.par, None);
+        assert_eq!(
+            level.par(),
+            Par {
+                time: 40.,
+                moves: 4
+            }
+        );
+    }
+}
@@ -1,9 +1,36 @@
+pub mod controls;
 mod cursor;
-mod piece;
+pub mod generator;
+pub mod hint;
+pub mod history;
+pub mod level;
+pub mod menu;
+pub mod piece;
+pub mod profile;
+pub mod puzzle;
+pub mod rating;
+pub mod save;
+pub mod score;
+pub mod solver;
+pub mod state;
+pub mod storage;
+pub mod versus;
 
 use bevy::prelude::*;
 use bevy::render::camera::WindowOrigin;
-use piece::{GameState, SQUARE_WIDTH, board::{self, Board}};
+use controls::{Actions, InputAction};
+use history::{Action, ActionKind, History, PieceState};
+use piece::{
+    board::{self, Board, PlacementResult},
+    grid::GridPos,
+    world::WorldPos,
+    Dragging, GridTransform, PieceId, PieceShape, Placed, SQUARE_WIDTH,
+};
+use score::Score;
+use state::{AppState, GameMode};
+
+pub const WINDOW_WIDTH: f32 = 800.;
+pub const WINDOW_HEIGHT: f32 = 600.;
 
 // Plugin
 pub struct GamePlugin;
@@ -12,18 +39,37 @@ impl Plugin for GamePlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.insert_resource(WindowDescriptor {
             title: "T-Triste".to_string(),
-            width: 800.,
-            height: 600.,
+            width: WINDOW_WIDTH,
+            height: WINDOW_HEIGHT,
             vsync: true,
             ..Default::default()
         })
         .insert_resource(ClearColor(Color::rgb(1., 0.90, 1.)))
         .add_plugins(DefaultPlugins)
+        .add_state(AppState::MainMenu)
         .add_startup_system(setup_camera.system())
+        .add_plugin(controls::ControlsPlugin)
         .add_plugin(cursor::CursorPlugin)
+        .add_plugin(level::LevelPlugin)
         .add_plugin(board::BoardPlugin)
         .add_plugin(piece::PiecePlugin)
-        .add_system(incrust_in_board.system());
+        .add_plugin(puzzle::PuzzlePlugin)
+        .add_plugin(score::ScorePlugin)
+        .add_plugin(hint::HintPlugin)
+        .add_plugin(piece::keyboard::KeyboardPlugin)
+        .add_plugin(history::HistoryPlugin)
+        .add_plugin(menu::MenuPlugin)
+        .add_plugin(save::SavePlugin)
+        .add_plugin(profile::ProfilePlugin)
+        .add_plugin(versus::VersusPlugin)
+        .add_system_set(
+            SystemSet::on_update(AppState::Playing).with_system(
+                incrust_in_board
+                    .system()
+                    .label("incrust_in_board")
+                    .before("release_piece"),
+            ),
+        );
     }
 }
 
@@ -34,50 +80,106 @@ fn setup_camera(mut commands: Commands) {
         camera.orthographic_projection.window_origin = WindowOrigin::BottomLeft;
         camera
     });
+    // Menus are drawn by the UI camera
+    commands.spawn_bundle(UiCameraBundle::default());
 }
 
+#[allow(clippy::too_many_arguments)]
 fn incrust_in_board(
-    mut game_state: NonSendMut<GameState>,
-    board: Option<Res<Board>>,
-    mouse_button_input: Res<Input<MouseButton>>,
+    mut commands: Commands,
+    mut pieces: Query<(Entity, &PieceId, &PieceShape, &mut GridTransform, &Dragging)>,
+    board: Option<ResMut<Board>>,
+    actions: Res<Actions>,
+    windows: Res<Windows>,
+    mode: Res<GameMode>,
+    mut score: ResMut<Score>,
+    mut history: ResMut<History>,
 ) {
-    if !mouse_button_input.just_released(MouseButton::Left) || 
-    board.is_none() {
+    if !actions.just_released(InputAction::PointerGrab) || board.is_none() {
         return;
     }
 
-    let board = board.unwrap();
+    let mut board = board.unwrap();
 
-    // The issue was that the code expected pixel perfect placement.
-    // Add a 5% acceptance factor.
-    // We could put this in a method to clean up the code ?
-    let adjusted_min_x = board.min_x * 0.95;
-    let adjusted_min_y = board.min_y * 0.95;
-    let adjusted_max_x = board.max_x * 1.05;
-    let adjusted_max_y = board.max_y * 1.05;
+    // The mouse drags one piece at a time
+    let (entity, id, shape, mut grid_transform, dragging) = match pieces.iter_mut().next() {
+        Some(piece) => piece,
+        None => return,
+    };
+    let positions = grid_transform.positions(shape);
+    let dropped = PieceState::new(*grid_transform, false);
 
-    // We take the first moving piece
-    // TODO: This could be improved
-    let moving_piece_optional = game_state.0.iter_mut().find(|piece| piece.is_moving());
-    if moving_piece_optional.is_none() {
-        return;
+    // The board pulls the piece on its cells if it was dropped close enough.
+    match board.place(*id, &positions) {
+        PlacementResult::Placed { offset } => {
+            grid_transform.translate(offset);
+            commands.entity(entity).insert(Placed);
+            score.moves += 1;
+            history.join(Action {
+                kind: ActionKind::Place,
+                piece: *id,
+                before: dropped,
+                after: PieceState::new(*grid_transform, true),
+            });
+        }
+        // Pieces can be left anywhere beside the board
+        PlacementResult::OutOfBounds if is_beside_board(&board, &windows, *mode, &positions) => {}
+        // Otherwise the piece goes back where it was picked up
+        _ => {
+            dragging.put_back(&mut grid_transform);
+            let back = PieceState::new(*grid_transform, false);
+            history.join(Action {
+                kind: ActionKind::Move,
+                piece: *id,
+                before: dropped,
+                after: back,
+            });
+            // Another piece may have been put on its cells in the meantime,
+            // then it stays off the board
+            if dragging.start_placed {
+                if let PlacementResult::Placed { offset } =
+                    board.place(*id, &grid_transform.positions(shape))
+                {
+                    grid_transform.translate(offset);
+                    commands.entity(entity).insert(Placed);
+                    history.join(Action {
+                        kind: ActionKind::Place,
+                        piece: *id,
+                        before: back,
+                        after: PieceState::new(*grid_transform, true),
+                    });
+                }
+            }
+        }
     }
-    let moving_piece = moving_piece_optional.unwrap();
-
-    // TODO: Find the exact board position that is being filled
-    let in_board = moving_piece.positions().iter().all(|t| {
-        adjusted_min_x <= t.x
-            && t.x <= adjusted_max_x
-            && adjusted_min_y <= t.y
-            && t.y <= adjusted_max_y
-    });
+    history.drop_unchanged_step();
+}
 
-    println!("BEFORE");
-    println!("{:?}", &moving_piece.positions());
-    if in_board {
-        moving_piece.snap();
-        // TODO: we are once again iterating over the transform. This is not efficient.
-        // TODO: Save the board squares that are filled.
-    }
-    println!("{:?}", &moving_piece.positions());
+pub(crate) fn is_beside_board(
+    board: &Board,
+    windows: &Windows,
+    mode: GameMode,
+    positions: &[GridPos],
+) -> bool {
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return false,
+    };
+    // In a race each player keeps to the half of the window with their board
+    let (left, right) = match mode {
+        GameMode::Solo => (0., window.width()),
+        GameMode::Versus if board.origin.to_world(0.).x < window.width() / 2. => {
+            (0., window.width() / 2.)
+        }
+        GameMode::Versus => (window.width() / 2., window.width()),
+    };
+    let half_width = (SQUARE_WIDTH / 2) as f32;
+    positions.iter().all(|position| {
+        let center = position.to_world(0.);
+        !board.contains(position)
+            && left + half_width <= center.x
+            && center.x <= right - half_width
+            && half_width <= center.y
+            && center.y <= window.height() - half_width
+    })
 }
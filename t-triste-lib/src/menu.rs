@@ -0,0 +1,469 @@
+use bevy::{app::AppExit, ecs::schedule::StateError, prelude::*};
+
+use crate::{
+    controls::{Actions, InputAction},
+    level::{CurrentLevel, Difficulty, LevelPack},
+    piece::{keyboard::Lifted, Dragging},
+    profile::Profile,
+    puzzle::PuzzleSolved,
+    score::stars_text,
+    state::{despawn_all, AppState, GameMode},
+    versus::{Player, Winner},
+};
+
+pub(crate) const FONT: &str = "fonts/DejaVuSansMono.ttf";
+const TITLE_SIZE: f32 = 48.;
+const TEXT_SIZE: f32 = 24.;
+
+// Plugins
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<MenuStyle>()
+            .insert_resource(MenuSelection(0))
+            .insert_resource(LevelResult(None))
+            .add_system_set(
+                SystemSet::on_enter(AppState::MainMenu).with_system(spawn_main_menu.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::MainMenu)
+                    .with_system(despawn_all::<MenuScreen>.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(AppState::LevelSelect).with_system(spawn_level_select.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::LevelSelect)
+                    .with_system(despawn_all::<MenuScreen>.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(AppState::Paused).with_system(spawn_pause_menu.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Paused)
+                    .with_system(despawn_all::<MenuScreen>.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(AppState::LevelComplete)
+                    .with_system(spawn_victory_screen.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::LevelComplete)
+                    .with_system(despawn_all::<MenuScreen>.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(pause_game.system())
+                    .with_system(complete_level.system().after("check_solved")),
+            )
+            .add_system(navigate_menu.system().label("navigate_menu"))
+            .add_system(highlight_entries.system().after("navigate_menu"));
+    }
+}
+
+// Components
+// Root of a menu screen, despawned with everything in it
+struct MenuScreen;
+
+// What going back does on this screen
+struct Menu {
+    back: Option<MenuAction>,
+}
+
+// A button of the menu, entries are numbered from the top
+struct MenuEntry {
+    index: usize,
+    action: MenuAction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MenuAction {
+    Play,
+    Versus,
+    Quit,
+    StartLevel(usize),
+    MainMenu,
+    Resume,
+    Restart,
+    NextLevel,
+}
+
+// Resources
+struct MenuStyle {
+    font: Handle<Font>,
+    background: Handle<ColorMaterial>,
+    entry: Handle<ColorMaterial>,
+    selected: Handle<ColorMaterial>,
+}
+
+impl FromWorld for MenuStyle {
+    fn from_world(world: &mut World) -> Self {
+        let font = world
+            .get_resource::<AssetServer>()
+            .expect("The asset server is missing")
+            .load(FONT);
+        let mut materials = world
+            .get_resource_mut::<Assets<ColorMaterial>>()
+            .expect("The color materials are missing");
+        MenuStyle {
+            font,
+            background: materials.add(Color::rgba(0.2, 0.1, 0.2, 0.8).into()),
+            entry: materials.add(Color::rgb(0.60, 0.40, 0.).into()),
+            selected: materials.add(Color::rgb(0.85, 0.60, 0.1).into()),
+        }
+    }
+}
+
+// The entry chosen with the keyboard or the gamepad
+struct MenuSelection(usize);
+
+// How the last level was solved, shown on the victory screen
+struct LevelResult(Option<PuzzleSolved>);
+
+// Systems
+fn spawn_main_menu(
+    mut commands: Commands,
+    style: Res<MenuStyle>,
+    mut selection: ResMut<MenuSelection>,
+) {
+    selection.0 = 0;
+    spawn_menu(
+        &mut commands,
+        &style,
+        "T-Triste",
+        &[],
+        &[
+            ("Jouer".to_string(), MenuAction::Play),
+            ("Duel".to_string(), MenuAction::Versus),
+            ("Quitter".to_string(), MenuAction::Quit),
+        ],
+        None,
+    );
+}
+
+// Only unlocked levels can be chosen, with the best time of completed ones
+fn spawn_level_select(
+    mut commands: Commands,
+    style: Res<MenuStyle>,
+    pack: Res<LevelPack>,
+    profile: Res<Profile>,
+    current: Res<CurrentLevel>,
+    mut selection: ResMut<MenuSelection>,
+) {
+    let mut entries: Vec<(String, MenuAction)> = pack
+        .levels
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| profile.is_unlocked(&pack, *index))
+        .map(|(index, level)| {
+            let difficulty = match level.difficulty {
+                Difficulty::Easy => "facile",
+                Difficulty::Medium => "moyen",
+                Difficulty::Hard => "difficile",
+            };
+            let best = match profile.record(&pack.name, &level.name) {
+                Some(record) => format!(
+                    " {} {:.1} s",
+                    stars_text(record.best_stars),
+                    record.best_time
+                ),
+                None => String::new(),
+            };
+            (
+                format!("{}. {} ({}){}", index + 1, level.name, difficulty, best),
+                MenuAction::StartLevel(index),
+            )
+        })
+        .collect();
+    selection.0 = entries
+        .iter()
+        .position(|(_, action)| *action == MenuAction::StartLevel(current.0))
+        .unwrap_or(0);
+    let locked = pack.levels.len() - entries.len();
+    let lines = if locked > 0 {
+        vec![format!("{} niveau(x) à débloquer", locked)]
+    } else {
+        vec![]
+    };
+    entries.push(("Retour".to_string(), MenuAction::MainMenu));
+    spawn_menu(
+        &mut commands,
+        &style,
+        "Niveaux",
+        &lines,
+        &entries,
+        Some(MenuAction::MainMenu),
+    );
+}
+
+fn spawn_pause_menu(
+    mut commands: Commands,
+    style: Res<MenuStyle>,
+    mut selection: ResMut<MenuSelection>,
+) {
+    selection.0 = 0;
+    spawn_menu(
+        &mut commands,
+        &style,
+        "Pause",
+        &[],
+        &[
+            ("Reprendre".to_string(), MenuAction::Resume),
+            ("Recommencer".to_string(), MenuAction::Restart),
+            ("Menu principal".to_string(), MenuAction::MainMenu),
+        ],
+        Some(MenuAction::Resume),
+    );
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_victory_screen(
+    mut commands: Commands,
+    style: Res<MenuStyle>,
+    pack: Res<LevelPack>,
+    current: Res<CurrentLevel>,
+    profile: Res<Profile>,
+    mode: Res<GameMode>,
+    winner: Res<Winner>,
+    result: Res<LevelResult>,
+    mut selection: ResMut<MenuSelection>,
+) {
+    selection.0 = 0;
+    // A race only tells who filled their board first
+    if *mode == GameMode::Versus {
+        let (title, controls) = match winner.0 {
+            Some(Player::Two) => ("Joueur 2 gagne !", "(clavier / manette)"),
+            _ => ("Joueur 1 gagne !", "(souris)"),
+        };
+        spawn_menu(
+            &mut commands,
+            &style,
+            title,
+            &[controls.to_string()],
+            &[
+                ("Rejouer".to_string(), MenuAction::Restart),
+                ("Menu principal".to_string(), MenuAction::MainMenu),
+            ],
+            None,
+        );
+        return;
+    }
+    let mut lines = match &result.0 {
+        Some(solved) => vec![
+            stars_text(solved.stars),
+            format!("Temps : {:.1} s", solved.score.elapsed.as_secs_f32()),
+            format!("Coups : {}", solved.score.moves),
+            format!("Indices : {}", solved.score.hints),
+        ],
+        None => vec![],
+    };
+    if let Some(record) = profile.record(&pack.name, &pack.levels[current.0].name) {
+        lines.push(format!("Meilleur temps : {:.1} s", record.best_time));
+    }
+    let mut entries = vec![];
+    // Solving the level unlocked the next one
+    if current.0 + 1 < pack.levels.len() && profile.is_unlocked(&pack, current.0 + 1) {
+        entries.push(("Niveau suivant".to_string(), MenuAction::NextLevel));
+    }
+    entries.push(("Rejouer".to_string(), MenuAction::Restart));
+    entries.push(("Menu principal".to_string(), MenuAction::MainMenu));
+    spawn_menu(&mut commands, &style, "Bravo !", &lines, &entries, None);
+}
+
+// Escape pauses the game, unless it is used to put back a piece
+#[allow(clippy::type_complexity)]
+fn pause_game(
+    actions: Res<Actions>,
+    mut state: ResMut<State<AppState>>,
+    moving: Query<Entity, Or<(With<Dragging>, With<Lifted>)>>,
+) {
+    if actions.just_pressed(InputAction::Cancel) && moving.iter().next().is_none() {
+        change_state(state.push(AppState::Paused));
+    }
+}
+
+fn complete_level(
+    mut solved_events: EventReader<PuzzleSolved>,
+    mut result: ResMut<LevelResult>,
+    mut state: ResMut<State<AppState>>,
+) {
+    if let Some(solved) = solved_events.iter().last() {
+        result.0 = Some(solved.clone());
+        change_state(state.push(AppState::LevelComplete));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn navigate_menu(
+    mut actions: ResMut<Actions>,
+    menus: Query<&Menu>,
+    entries: Query<(&MenuEntry, &Interaction), Changed<Interaction>>,
+    all_entries: Query<&MenuEntry>,
+    mut selection: ResMut<MenuSelection>,
+    mut current: ResMut<CurrentLevel>,
+    mut mode: ResMut<GameMode>,
+    mut state: ResMut<State<AppState>>,
+    mut exit_events: EventWriter<AppExit>,
+) {
+    let menu = match menus.iter().next() {
+        Some(menu) => menu,
+        None => return,
+    };
+    let count = all_entries.iter().count();
+    if count == 0 {
+        return;
+    }
+    selection.0 = selection.0.min(count - 1);
+
+    let mut chosen = None;
+    // The mouse moves the selection too, so both stay in sync
+    for (entry, interaction) in entries.iter() {
+        match interaction {
+            Interaction::Clicked => chosen = Some(entry.action),
+            Interaction::Hovered => selection.0 = entry.index,
+            Interaction::None => (),
+        }
+    }
+    if actions.just_pressed(InputAction::MoveUp) {
+        selection.0 = (selection.0 + count - 1) % count;
+    }
+    if actions.just_pressed(InputAction::MoveDown) {
+        selection.0 = (selection.0 + 1) % count;
+    }
+    if actions.just_pressed(InputAction::Place) {
+        chosen = all_entries
+            .iter()
+            .find(|entry| entry.index == selection.0)
+            .map(|entry| entry.action);
+    }
+    if actions.just_pressed(InputAction::Cancel) {
+        chosen = chosen.or(menu.back);
+    }
+    if chosen.is_none() {
+        return;
+    }
+    // The game must not see the press that closed the menu
+    actions.consume(InputAction::PointerGrab);
+    actions.consume(InputAction::Place);
+    actions.consume(InputAction::Cancel);
+
+    match chosen {
+        Some(MenuAction::Play) => {
+            *mode = GameMode::Solo;
+            change_state(state.set(AppState::LevelSelect));
+        }
+        Some(MenuAction::Versus) => {
+            *mode = GameMode::Versus;
+            change_state(state.set(AppState::LevelSelect));
+        }
+        Some(MenuAction::Quit) => exit_events.send(AppExit),
+        Some(MenuAction::StartLevel(index)) => {
+            current.0 = index;
+            change_state(state.set(AppState::Playing));
+        }
+        // Replacing the stack leaves Playing too, so the level is cleaned up
+        Some(MenuAction::MainMenu) => change_state(state.replace(AppState::MainMenu)),
+        Some(MenuAction::Resume) => change_state(state.pop()),
+        Some(MenuAction::Restart) => change_state(state.replace(AppState::Playing)),
+        Some(MenuAction::NextLevel) => {
+            current.0 += 1;
+            change_state(state.replace(AppState::Playing));
+        }
+        None => (),
+    }
+}
+
+fn highlight_entries(
+    style: Res<MenuStyle>,
+    selection: Res<MenuSelection>,
+    mut entries: Query<(&MenuEntry, &mut Handle<ColorMaterial>)>,
+) {
+    for (entry, mut material) in entries.iter_mut() {
+        let wanted = if entry.index == selection.0 {
+            &style.selected
+        } else {
+            &style.entry
+        };
+        if *material != *wanted {
+            *material = wanted.clone();
+        }
+    }
+}
+
+// A screen centered in the window: a title, some lines of text then the entries
+fn spawn_menu(
+    commands: &mut Commands,
+    style: &MenuStyle,
+    title: &str,
+    lines: &[String],
+    entries: &[(String, MenuAction)],
+    back: Option<MenuAction>,
+) {
+    let text = |value: &str, font_size: f32| TextBundle {
+        style: Style {
+            margin: Rect::all(Val::Px(8.)),
+            ..Default::default()
+        },
+        text: Text::with_section(
+            value,
+            TextStyle {
+                font: style.font.clone(),
+                font_size,
+                color: Color::WHITE,
+            },
+            Default::default(),
+        ),
+        ..Default::default()
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                // Children are laid out from the top
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: style.background.clone(),
+            ..Default::default()
+        })
+        .insert(MenuScreen)
+        .insert(Menu { back })
+        .with_children(|parent| {
+            parent.spawn_bundle(text(title, TITLE_SIZE));
+            for line in lines {
+                parent.spawn_bundle(text(line, TEXT_SIZE));
+            }
+            for (index, (label, action)) in entries.iter().enumerate() {
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(360.), Val::Px(44.)),
+                            margin: Rect::all(Val::Px(6.)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        material: style.entry.clone(),
+                        ..Default::default()
+                    })
+                    .insert(MenuEntry {
+                        index,
+                        action: *action,
+                    })
+                    .with_children(|button| {
+                        button.spawn_bundle(text(label, TEXT_SIZE));
+                    });
+            }
+        });
+}
+
+// A change can be refused when another one is already queued this frame
+fn change_state(result: Result<(), StateError>) {
+    if let Err(error) = result {
+        warn!("Cannot change the screen: {:?}", error);
+    }
+}
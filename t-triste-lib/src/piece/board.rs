@@ -1,16 +1,27 @@
-use bevy::prelude::*;
+use std::collections::HashMap;
 
-use crate::piece::SQUARE_WIDTH;
+use bevy::prelude::*;
 
-use super::piece_builder::PieceBuilder;
+use crate::{
+    piece::{grid::GridPos, world::WorldPos, PieceId, SQUARE_WIDTH},
+    state::{despawn_all, AppState},
+};
 
 // Plugins
 pub struct BoardPlugin;
 
 impl Plugin for BoardPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.insert_resource(Board::new(300, 250))
-            .add_startup_system(draw_board.system());
+        // The board itself is inserted by the level
+        app.init_resource::<BoardMaterial>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing)
+                    .with_system(draw_board.system().after("spawn_level")),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Playing)
+                    .with_system(despawn_all::<BoardPosition>.system()),
+            );
     }
 }
 
@@ -19,64 +30,248 @@ impl Plugin for BoardPlugin {
 // Marker component
 struct BoardPosition;
 
-// This represent a board. For now the size is fixed
-// * * * *
-// * * * *
+// Where a piece lands when it is dropped on the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlacementResult {
+    // The piece fills the board once moved by `offset` cells
+    Placed { offset: GridPos },
+    // Some cells are outside of the board, even within the snap radius
+    OutOfBounds,
+    // The piece fits in the board but covers a cell filled by `owner`
+    Overlap { owner: PieceId },
+}
+
+// How far (in cells) a dropped piece can be pulled to fit in the board
+pub const DEFAULT_SNAP_RADIUS: i32 = 1;
+
+// Resources
+// The color of the board cells, shared by every level
+pub struct BoardMaterial(Handle<ColorMaterial>);
+
+impl FromWorld for BoardMaterial {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world
+            .get_resource_mut::<Assets<ColorMaterial>>()
+            .expect("The color materials are missing");
+        BoardMaterial(materials.add(Color::rgb(0.60, 0.40, 0.).into()))
+    }
+}
+
+// This represent a board. Its shape comes from the level and can have holes
 // * * * *
+// * . * *
+// * * *
 pub struct Board {
-    pub positions: Vec<Vec3>,
-    pub min_x: f32,
-    pub min_y: f32,
-    pub max_x: f32,
-    pub max_y: f32,
-    // true if the position is filled
-    // vec[bool[]] ?
+    // Bottom left cell of the board
+    pub origin: GridPos,
+    pub snap_radius: i32,
+    // Cells relative to the origin
+    cells: Vec<GridPos>,
+    // The piece filling each filled cell, relative to the origin
+    filled: HashMap<GridPos, PieceId>,
 }
 
 impl Board {
-    fn new(start_x: i32, start_y: i32) -> Self {
-        let nb_rows = 3;
-        let nb_cols = 5;
-        let mut positions = vec![];
-        for i in 0..nb_rows {
-            positions.append(&mut PieceBuilder::new_horizontal_rectangle(
-                start_x,
-                start_y + (i * SQUARE_WIDTH),
-                nb_cols,
-                0.,
-            ));
-        }
+    pub fn new(origin: GridPos, cells: Vec<GridPos>) -> Self {
         Board {
-            positions,
-            min_x: start_x as f32,
-            min_y: start_y as f32,
-            max_x: (start_x + (nb_cols * SQUARE_WIDTH)) as f32,
-            max_y: (start_y + (nb_rows * SQUARE_WIDTH)) as f32,
+            origin,
+            snap_radius: DEFAULT_SNAP_RADIUS,
+            cells,
+            filled: HashMap::new(),
         }
     }
+
+    // Cells of the board in the game grid
+    pub fn positions(&self) -> Vec<GridPos> {
+        self.cells.iter().map(|cell| *cell + self.origin).collect()
+    }
+
+    pub fn contains(&self, position: &GridPos) -> bool {
+        self.cells.contains(&(*position - self.origin))
+    }
+
+    pub fn is_filled(&self, cell: &GridPos) -> bool {
+        self.filled.contains_key(&(*cell - self.origin))
+    }
+
+    pub fn owner(&self, cell: &GridPos) -> Option<PieceId> {
+        self.filled.get(&(*cell - self.origin)).copied()
+    }
+
+    pub fn is_placed(&self, piece: PieceId) -> bool {
+        self.filled.values().any(|owner| *owner == piece)
+    }
+
+    pub fn free_cells(&self) -> Vec<GridPos> {
+        self.positions()
+            .into_iter()
+            .filter(|cell| !self.is_filled(cell))
+            .collect()
+    }
+
+    // Every cell is covered, and only once as pieces can't overlap
+    pub fn is_complete(&self) -> bool {
+        self.cells.iter().all(|cell| self.filled.contains_key(cell))
+    }
+
+    // Find where the piece would land, without filling anything.
+    // The piece is pulled to the closest position within the snap radius
+    // where it is fully inside the board, then it must not cover another piece.
+    pub fn resolve_placement(&self, piece: PieceId, cells: &[GridPos]) -> PlacementResult {
+        let mut offsets = vec![];
+        for col in -self.snap_radius..=self.snap_radius {
+            for row in -self.snap_radius..=self.snap_radius {
+                offsets.push(GridPos::new(col, row));
+            }
+        }
+        offsets.sort_by_key(|offset| offset.col.abs() + offset.row.abs());
+
+        let offset = offsets
+            .into_iter()
+            .find(|offset| cells.iter().all(|cell| self.contains(&(*cell + *offset))));
+        match offset {
+            None => PlacementResult::OutOfBounds,
+            Some(offset) => match cells
+                .iter()
+                .filter_map(|cell| self.owner(&(*cell + offset)))
+                .find(|owner| *owner != piece)
+            {
+                None => PlacementResult::Placed { offset },
+                Some(owner) => PlacementResult::Overlap { owner },
+            },
+        }
+    }
+
+    // Fill the board with the piece, replacing where it was before.
+    // Nothing changes unless the piece is placed.
+    pub fn place(&mut self, piece: PieceId, cells: &[GridPos]) -> PlacementResult {
+        let result = self.resolve_placement(piece, cells);
+        if let PlacementResult::Placed { offset } = result {
+            self.remove(piece);
+            for cell in cells {
+                self.filled.insert(*cell + offset - self.origin, piece);
+            }
+        }
+        result
+    }
+
+    pub fn remove(&mut self, piece: PieceId) {
+        self.filled.retain(|_, owner| *owner != piece);
+    }
 }
 
 // Systems
-fn draw_board(
-    board: Res<Board>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    mut commands: Commands,
+fn draw_board(board: Res<Board>, material: Res<BoardMaterial>, mut commands: Commands) {
+    spawn_board_sprites(&mut commands, &material, &board);
+}
+
+// The cells of a board, removed when leaving the level
+pub(crate) fn spawn_board_sprites(
+    commands: &mut Commands,
+    material: &BoardMaterial,
+    board: &Board,
 ) {
-    let material = materials.add(Color::rgb(0.60, 0.40, 0.).into());
-    board
-        .positions
-        .iter()
-        .for_each(|position| {
-            commands
-                .spawn_bundle(SpriteBundle {
-                    material: material.clone(),
-                    sprite: Sprite::new(Vec2::new(
-                        (SQUARE_WIDTH - 1) as f32,
-                        (SQUARE_WIDTH - 1) as f32,
-                    )),
-                    transform: Transform::from_translation(*position),
-                    ..Default::default()
-                })
-                .insert(BoardPosition);
-        });
+    board.positions().iter().for_each(|position| {
+        commands
+            .spawn_bundle(SpriteBundle {
+                material: material.0.clone(),
+                sprite: Sprite::new(Vec2::new(
+                    (SQUARE_WIDTH - 1) as f32,
+                    (SQUARE_WIDTH - 1) as f32,
+                )),
+                transform: Transform::from_translation(position.to_world(0.)),
+                ..Default::default()
+            })
+            .insert(BoardPosition);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 3x2 board starting at (10, 10)
+    fn board() -> Board {
+        let mut cells = vec![];
+        for col in 0..3 {
+            for row in 0..2 {
+                cells.push(GridPos::new(col, row));
+            }
+        }
+        Board::new(GridPos::new(10, 10), cells)
+    }
+
+    fn domino(col: i32, row: i32) -> Vec<GridPos> {
+        vec![GridPos::new(col, row), GridPos::new(col + 1, row)]
+    }
+
+    #[test]
+    fn resolve_placement_inside_the_board() {
+        let board = board();
+        assert_eq!(
+            board.resolve_placement(PieceId(0), &domino(10, 10)),
+            PlacementResult::Placed {
+                offset: GridPos::default()
+            }
+        );
+    }
+
+    #[test]
+    fn resolve_placement_snaps_within_the_radius() {
+        let board = board();
+        // One cell too far on the right is pulled back
+        assert_eq!(
+            board.resolve_placement(PieceId(0), &domino(12, 11)),
+            PlacementResult::Placed {
+                offset: GridPos::new(-1, 0)
+            }
+        );
+        // Beyond the radius the piece stays out
+        assert_eq!(
+            board.resolve_placement(PieceId(0), &domino(13, 11)),
+            PlacementResult::OutOfBounds
+        );
+        let mut wider = board;
+        wider.snap_radius = 2;
+        assert_eq!(
+            wider.resolve_placement(PieceId(0), &domino(13, 11)),
+            PlacementResult::Placed {
+                offset: GridPos::new(-2, 0)
+            }
+        );
+    }
+
+    #[test]
+    fn resolve_placement_out_of_bounds() {
+        let board = board();
+        // Longer than the board in any direction
+        let line: Vec<GridPos> = (0..4).map(|col| GridPos::new(10 + col, 10)).collect();
+        assert_eq!(
+            board.resolve_placement(PieceId(0), &line),
+            PlacementResult::OutOfBounds
+        );
+    }
+
+    #[test]
+    fn resolve_placement_overlap() {
+        let mut board = board();
+        assert!(matches!(
+            board.place(PieceId(0), &domino(10, 10)),
+            PlacementResult::Placed { .. }
+        ));
+        assert_eq!(
+            board.resolve_placement(PieceId(1), &domino(11, 10)),
+            PlacementResult::Overlap { owner: PieceId(0) }
+        );
+        // A piece does not overlap itself
+        assert_eq!(
+            board.resolve_placement(PieceId(0), &domino(11, 10)),
+            PlacementResult::Placed {
+                offset: GridPos::default()
+            }
+        );
+        // Nothing was filled by resolving
+        assert!(!board.is_placed(PieceId(1)));
+        assert_eq!(board.free_cells().len(), 4);
+    }
 }
@@ -0,0 +1,383 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::piece::{
+    grid::{parse_cells, GridPos},
+    Piece,
+};
+
+// Every free polyomino of order 1 to 6: pieces that are the same once rotated or mirrored
+// are only listed once. Shapes are drawn like the board of a level, top row first.
+
+const PALETTE: [(u8, u8, u8); 12] = [
+    (0xff, 0x1f, 0x08),
+    (0x75, 0xfa, 0xff),
+    (0xd4, 0xff, 0x2e),
+    (0xad, 0x1a, 0xff),
+    (0x03, 0xff, 0x6c),
+    (0xff, 0xa5, 0x00),
+    (0x1e, 0x5b, 0xff),
+    (0xff, 0x5c, 0xc8),
+    (0x8b, 0x5a, 0x2b),
+    (0x00, 0x99, 0x88),
+    (0xff, 0xe1, 0x00),
+    (0x7a, 0x7a, 0x7a),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Polyomino {
+    Monomino,
+    Domino,
+    Tromino(Tromino),
+    Tetromino(Tetromino),
+    Pentomino(Pentomino),
+    Hexomino(Hexomino),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Tromino {
+    I,
+    L,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Tetromino {
+    I,
+    O,
+    T,
+    S,
+    L,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Pentomino {
+    F,
+    I,
+    L,
+    N,
+    P,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+}
+
+// Hexominoes have no common names, they are numbered from the longest to the most compact
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Hexomino {
+    H01,
+    H02,
+    H03,
+    H04,
+    H05,
+    H06,
+    H07,
+    H08,
+    H09,
+    H10,
+    H11,
+    H12,
+    H13,
+    H14,
+    H15,
+    H16,
+    H17,
+    H18,
+    H19,
+    H20,
+    H21,
+    H22,
+    H23,
+    H24,
+    H25,
+    H26,
+    H27,
+    H28,
+    H29,
+    H30,
+    H31,
+    H32,
+    H33,
+    H34,
+    H35,
+}
+
+impl Polyomino {
+    pub fn all() -> Vec<Polyomino> {
+        (1..=6).flat_map(Polyomino::of_order).collect()
+    }
+
+    // The free polyominoes made of `order` squares, none above 6
+    pub fn of_order(order: usize) -> Vec<Polyomino> {
+        match order {
+            1 => vec![Polyomino::Monomino],
+            2 => vec![Polyomino::Domino],
+            3 => Tromino::ALL
+                .iter()
+                .map(|p| Polyomino::Tromino(*p))
+                .collect(),
+            4 => Tetromino::ALL
+                .iter()
+                .map(|p| Polyomino::Tetromino(*p))
+                .collect(),
+            5 => Pentomino::ALL
+                .iter()
+                .map(|p| Polyomino::Pentomino(*p))
+                .collect(),
+            6 => Hexomino::ALL
+                .iter()
+                .map(|p| Polyomino::Hexomino(*p))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    pub fn order(self) -> usize {
+        match self {
+            Polyomino::Monomino => 1,
+            Polyomino::Domino => 2,
+            Polyomino::Tromino(_) => 3,
+            Polyomino::Tetromino(_) => 4,
+            Polyomino::Pentomino(_) => 5,
+            Polyomino::Hexomino(_) => 6,
+        }
+    }
+
+    // Like "Pentomino F" or "Hexomino 12"
+    pub fn name(self) -> String {
+        match self {
+            Polyomino::Monomino => "Monomino".to_string(),
+            Polyomino::Domino => "Domino".to_string(),
+            Polyomino::Tromino(p) => format!("Tromino {:?}", p),
+            Polyomino::Tetromino(p) => format!("Tetromino {:?}", p),
+            Polyomino::Pentomino(p) => format!("Pentomino {:?}", p),
+            Polyomino::Hexomino(p) => format!("Hexomino {}", p.number()),
+        }
+    }
+
+    pub fn shape(self) -> &'static str {
+        match self {
+            Polyomino::Monomino => "X",
+            Polyomino::Domino => "XX",
+            Polyomino::Tromino(p) => p.shape(),
+            Polyomino::Tetromino(p) => p.shape(),
+            Polyomino::Pentomino(p) => p.shape(),
+            Polyomino::Hexomino(p) => p.shape(),
+        }
+    }
+
+    pub fn cells(self) -> Vec<GridPos> {
+        // Catalog shapes only use `X` and `.`
+        parse_cells(self.shape().lines()).unwrap()
+    }
+
+    pub fn color(self) -> Color {
+        let index = Polyomino::of_order(self.order())
+            .iter()
+            .position(|p| *p == self)
+            .unwrap();
+        let (red, green, blue) = PALETTE[index % PALETTE.len()];
+        Color::rgb_u8(red, green, blue)
+    }
+
+    pub fn build(self, position: GridPos) -> Box<dyn Piece> {
+        Box::new(CatalogPiece {
+            polyomino: self,
+            anchor: position,
+        })
+    }
+}
+
+impl Tromino {
+    pub const ALL: [Tromino; 2] = [Tromino::I, Tromino::L];
+
+    fn shape(self) -> &'static str {
+        match self {
+            Tromino::I => "XXX",
+            Tromino::L => "X.\nXX",
+        }
+    }
+}
+
+impl Tetromino {
+    pub const ALL: [Tetromino; 5] = [
+        Tetromino::I,
+        Tetromino::O,
+        Tetromino::T,
+        Tetromino::S,
+        Tetromino::L,
+    ];
+
+    fn shape(self) -> &'static str {
+        match self {
+            Tetromino::I => "XXXX",
+            Tetromino::O => "XX\nXX",
+            Tetromino::T => "XXX\n.X.",
+            Tetromino::S => ".XX\nXX.",
+            Tetromino::L => "X.\nX.\nXX",
+        }
+    }
+}
+
+impl Pentomino {
+    pub const ALL: [Pentomino; 12] = [
+        Pentomino::F,
+        Pentomino::I,
+        Pentomino::L,
+        Pentomino::N,
+        Pentomino::P,
+        Pentomino::T,
+        Pentomino::U,
+        Pentomino::V,
+        Pentomino::W,
+        Pentomino::X,
+        Pentomino::Y,
+        Pentomino::Z,
+    ];
+
+    fn shape(self) -> &'static str {
+        match self {
+            Pentomino::F => ".XX\nXX.\n.X.",
+            Pentomino::I => "XXXXX",
+            Pentomino::L => "X.\nX.\nX.\nXX",
+            Pentomino::N => ".X\nXX\nX.\nX.",
+            Pentomino::P => "XX\nXX\nX.",
+            Pentomino::T => "XXX\n.X.\n.X.",
+            Pentomino::U => "X.X\nXXX",
+            Pentomino::V => "X..\nX..\nXXX",
+            Pentomino::W => "X..\nXX.\n.XX",
+            Pentomino::X => ".X.\nXXX\n.X.",
+            Pentomino::Y => ".X\nXX\n.X\n.X",
+            Pentomino::Z => "XX.\n.X.\n.XX",
+        }
+    }
+}
+
+impl Hexomino {
+    pub const ALL: [Hexomino; 35] = [
+        Hexomino::H01,
+        Hexomino::H02,
+        Hexomino::H03,
+        Hexomino::H04,
+        Hexomino::H05,
+        Hexomino::H06,
+        Hexomino::H07,
+        Hexomino::H08,
+        Hexomino::H09,
+        Hexomino::H10,
+        Hexomino::H11,
+        Hexomino::H12,
+        Hexomino::H13,
+        Hexomino::H14,
+        Hexomino::H15,
+        Hexomino::H16,
+        Hexomino::H17,
+        Hexomino::H18,
+        Hexomino::H19,
+        Hexomino::H20,
+        Hexomino::H21,
+        Hexomino::H22,
+        Hexomino::H23,
+        Hexomino::H24,
+        Hexomino::H25,
+        Hexomino::H26,
+        Hexomino::H27,
+        Hexomino::H28,
+        Hexomino::H29,
+        Hexomino::H30,
+        Hexomino::H31,
+        Hexomino::H32,
+        Hexomino::H33,
+        Hexomino::H34,
+        Hexomino::H35,
+    ];
+
+    // From 1 to 35
+    pub fn number(self) -> usize {
+        Hexomino::ALL.iter().position(|p| *p == self).unwrap() + 1
+    }
+
+    fn shape(self) -> &'static str {
+        match self {
+            Hexomino::H01 => "XXXXXX",
+            Hexomino::H02 => "....X\nXXXXX",
+            Hexomino::H03 => "...X.\nXXXXX",
+            Hexomino::H04 => "..X..\nXXXXX",
+            Hexomino::H05 => "...XX\nXXXX.",
+            Hexomino::H06 => "..XXX\nXXX..",
+            Hexomino::H07 => "..XX\nXXXX",
+            Hexomino::H08 => ".X.X\nXXXX",
+            Hexomino::H09 => "X..X\nXXXX",
+            Hexomino::H10 => "...X\n...X\nXXXX",
+            Hexomino::H11 => ".XX.\nXXXX",
+            Hexomino::H12 => "..X.\n..X.\nXXXX",
+            Hexomino::H13 => ".XXX\nXX.X",
+            Hexomino::H14 => ".XXX\nXXX.",
+            Hexomino::H15 => "...X\nXXXX\n...X",
+            Hexomino::H16 => "..X.\n..XX\nXXX.",
+            Hexomino::H17 => "...X\n..XX\nXXX.",
+            Hexomino::H18 => "..XX\n..X.\nXXX.",
+            Hexomino::H19 => "..X.\nXXX.\n..XX",
+            Hexomino::H20 => "..X.\n.XXX\nXX..",
+            Hexomino::H21 => "...X\n.XXX\nXX..",
+            Hexomino::H22 => "...X\nXXXX\n..X.",
+            Hexomino::H23 => "..XX\n.XX.\nXX..",
+            Hexomino::H24 => "...X\nXXXX\n.X..",
+            Hexomino::H25 => "...X\nXXXX\nX...",
+            Hexomino::H26 => "..X.\nXXXX\n..X.",
+            Hexomino::H27 => "..X.\nXXXX\n.X..",
+            Hexomino::H28 => "XXX\nXXX",
+            Hexomino::H29 => "..X\n.XX\nXXX",
+            Hexomino::H30 => "..X\nXXX\n.XX",
+            Hexomino::H31 => "..X\nX.X\nXXX",
+            Hexomino::H32 => "..X\nXXX\nX.X",
+            Hexomino::H33 => ".X.\nXXX\n.XX",
+            Hexomino::H34 => "..X\nXXX\nXX.",
+            Hexomino::H35 => ".X.\nXXX\nX.X",
+        }
+    }
+}
+
+// A catalog polyomino put on the grid
+pub struct CatalogPiece {
+    polyomino: Polyomino,
+    anchor: GridPos,
+}
+
+impl Piece for CatalogPiece {
+    fn shape(&self) -> Vec<GridPos> {
+        self.polyomino.cells()
+    }
+
+    fn color(&self) -> Color {
+        self.polyomino.color()
+    }
+
+    fn anchor(&self) -> GridPos {
+        self.anchor
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piece::polyomino::{canonical, free_polyominoes};
+
+    #[test]
+    fn catalog_lists_every_free_polyomino_once() {
+        for order in 1..=6 {
+            let mut shapes: Vec<Vec<GridPos>> = Polyomino::of_order(order)
+                .into_iter()
+                .map(|polyomino| {
+                    assert_eq!(polyomino.order(), order, "{}", polyomino.name());
+                    canonical(&polyomino.cells())
+                })
+                .collect();
+            shapes.sort();
+            assert_eq!(shapes, free_polyominoes(order), "order {}", order);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::ops::{Add, Sub};
+
+use serde::{Deserialize, Serialize};
+
+// A cell of the game grid.
+// Pieces and board only deal with cells, pixels are computed when rendering
+// (see `world`).
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct GridPos {
+    pub col: i32,
+    pub row: i32,
+}
+
+impl GridPos {
+    pub const fn new(col: i32, row: i32) -> Self {
+        GridPos { col, row }
+    }
+
+    // Quarter turn counterclockwise around `pivot`.
+    pub fn rotate_around(&self, pivot: GridPos) -> Self {
+        let delta = *self - pivot;
+        pivot + GridPos::new(-delta.row, delta.col)
+    }
+}
+
+// The cell closest to the center of mass of the cells, the first one on a tie.
+// Distances are compared on cells scaled by the number of cells to stay exact.
+pub fn centroid_cell(cells: &[GridPos]) -> GridPos {
+    let nb_cells = cells.len() as i32;
+    let sum = cells
+        .iter()
+        .fold(GridPos::default(), |sum, cell| sum + *cell);
+    *cells
+        .iter()
+        .min_by_key(|cell| {
+            let col = cell.col * nb_cells - sum.col;
+            let row = cell.row * nb_cells - sum.row;
+            col * col + row * row
+        })
+        .unwrap()
+}
+
+// Cells drawn as lines of text, top line first: `X` is a cell, `.` or ` ` a hole.
+// The last line is row 0. Fails on the first unknown character.
+pub fn parse_cells<'a, I>(lines: I) -> Result<Vec<GridPos>, char>
+where
+    I: DoubleEndedIterator<Item = &'a str>,
+{
+    let mut cells = vec![];
+    for (row, line) in lines.rev().enumerate() {
+        for (col, c) in line.chars().enumerate() {
+            match c {
+                'X' => cells.push(GridPos::new(col as i32, row as i32)),
+                '.' | ' ' => {}
+                _ => return Err(c),
+            }
+        }
+    }
+    Ok(cells)
+}
+
+impl Add for GridPos {
+    type Output = GridPos;
+
+    fn add(self, other: GridPos) -> GridPos {
+        GridPos::new(self.col + other.col, self.row + other.row)
+    }
+}
+
+impl Sub for GridPos {
+    type Output = GridPos;
+
+    fn sub(self, other: GridPos) -> GridPos {
+        GridPos::new(self.col - other.col, self.row - other.row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotate_around_turns_counterclockwise() {
+        let pivot = GridPos::new(2, 3);
+        let cell = GridPos::new(4, 3);
+        let turned = cell.rotate_around(pivot);
+        assert_eq!(turned, GridPos::new(2, 5));
+        assert_eq!(pivot.rotate_around(pivot), pivot);
+        // Four quarter turns go back to the start
+        let back = (0..4).fold(cell, |cell, _| cell.rotate_around(pivot));
+        assert_eq!(back, cell);
+    }
+
+    #[test]
+    fn centroid_cell_is_the_closest_to_the_center() {
+        // A line of three cells turns around its middle
+        let line = [GridPos::new(0, 0), GridPos::new(1, 0), GridPos::new(2, 0)];
+        assert_eq!(centroid_cell(&line), GridPos::new(1, 0));
+        // The L is heavier on its long side
+        let l = [
+            GridPos::new(0, 0),
+            GridPos::new(0, 1),
+            GridPos::new(0, 2),
+            GridPos::new(1, 0),
+        ];
+        assert_eq!(centroid_cell(&l), GridPos::new(0, 1));
+        // The first cell wins a tie
+        let square = [
+            GridPos::new(5, 5),
+            GridPos::new(6, 5),
+            GridPos::new(5, 6),
+            GridPos::new(6, 6),
+        ];
+        assert_eq!(centroid_cell(&square), GridPos::new(5, 5));
+    }
+
+    #[test]
+    fn parse_cells_reads_the_last_line_as_row_0() {
+        let cells = parse_cells(vec!["X.", "XX"].into_iter()).unwrap();
+        assert_eq!(
+            cells,
+            vec![GridPos::new(0, 0), GridPos::new(1, 0), GridPos::new(0, 1)]
+        );
+        let holes = parse_cells(vec!["X X"].into_iter()).unwrap();
+        assert_eq!(holes, vec![GridPos::new(0, 0), GridPos::new(2, 0)]);
+        assert_eq!(parse_cells(vec!["X#"].into_iter()), Err('#'));
+    }
+}
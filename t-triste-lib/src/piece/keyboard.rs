@@ -0,0 +1,319 @@
+use bevy::prelude::*;
+
+use crate::{
+    controls::{Actions, InputAction},
+    history::{Action, ActionKind, History, PieceState},
+    is_beside_board,
+    piece::{
+        board::{Board, PlacementResult},
+        grid::GridPos,
+        world::WorldPos,
+        Dragging, GridTransform, PieceId, PieceMaterials, PieceShape, Placed, SQUARE_WIDTH,
+    },
+    score::Score,
+    state::{despawn_all, AppState, GameMode},
+    versus::{player_board, Player, RivalBoard},
+};
+
+// The highlight is drawn right under the focused piece
+const FOCUS_Z: f32 = -0.05;
+const FOCUS_BORDER: i32 = 3;
+
+// Plugins
+// Playing without a mouse, with the keyboard or a gamepad: Tab picks a piece,
+// the arrows move it, R rotates it, F flips it, Enter drops it and Escape puts
+// it back where it was. See `controls` for the other buttons.
+pub struct KeyboardPlugin;
+
+impl Plugin for KeyboardPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(Focus(None))
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(focus_piece.system().label("focus_piece"))
+                    .with_system(keyboard_piece.system().after("focus_piece")),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Playing)
+                    .with_system(clear_focus.system())
+                    .with_system(despawn_all::<FocusCell>.system()),
+            )
+            .add_system(spawn_focus_cells.system().label("spawn_focus_cells"))
+            .add_system(draw_focus_cells.system().after("spawn_focus_cells"));
+    }
+}
+
+// Components
+// A piece moved with the keyboard, it is out of the board until dropped
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lifted {
+    pub start: GridTransform,
+    pub start_placed: bool,
+}
+
+// A square of the highlight, `offset` being its cell from the piece anchor
+struct FocusCell {
+    offset: GridPos,
+}
+
+// Resources
+// The piece the keyboard acts on
+pub struct Focus(pub Option<PieceId>);
+
+// Systems
+fn focus_piece(
+    actions: Res<Actions>,
+    mode: Res<GameMode>,
+    mut focus: ResMut<Focus>,
+    pieces: Query<(&PieceId, &Player)>,
+) {
+    let backward = actions.just_pressed(InputAction::PreviousPiece);
+    if !backward && !actions.just_pressed(InputAction::NextPiece) {
+        return;
+    }
+    let mut ids: Vec<PieceId> = pieces
+        .iter()
+        .filter(|(_, player)| **player == Player::with_keyboard(*mode))
+        .map(|(id, _)| *id)
+        .collect();
+    if ids.is_empty() {
+        return;
+    }
+    ids.sort_by_key(|id| id.0);
+
+    let next = match focus
+        .0
+        .and_then(|id| ids.iter().position(|other| *other == id))
+    {
+        Some(i) if backward => (i + ids.len() - 1) % ids.len(),
+        Some(i) => (i + 1) % ids.len(),
+        None if backward => ids.len() - 1,
+        None => 0,
+    };
+    focus.0 = Some(ids[next]);
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn keyboard_piece(
+    mut commands: Commands,
+    actions: Res<Actions>,
+    focus: Res<Focus>,
+    windows: Res<Windows>,
+    mode: Res<GameMode>,
+    mut board: ResMut<Board>,
+    mut rival_board: ResMut<RivalBoard>,
+    mut score: ResMut<Score>,
+    mut history: ResMut<History>,
+    mut pieces: Query<
+        (
+            Entity,
+            &PieceId,
+            &PieceShape,
+            &mut GridTransform,
+            Option<&Lifted>,
+            &Player,
+        ),
+        Without<Dragging>,
+    >,
+) {
+    let focused = match focus.0 {
+        Some(focused) => focused,
+        None => return,
+    };
+    let (entity, id, shape, mut grid_transform, lifted, player) =
+        match pieces.iter_mut().find(|(_, id, ..)| **id == focused) {
+            Some(piece) => piece,
+            None => return,
+        };
+    let board = player_board(*player, &mut board, &mut rival_board);
+
+    let delta = [
+        (InputAction::MoveLeft, GridPos::new(-1, 0)),
+        (InputAction::MoveRight, GridPos::new(1, 0)),
+        (InputAction::MoveUp, GridPos::new(0, 1)),
+        (InputAction::MoveDown, GridPos::new(0, -1)),
+    ]
+    .iter()
+    .filter(|(action, _)| actions.just_pressed(*action))
+    .fold(GridPos::default(), |delta, (_, step)| delta + *step);
+    if delta != GridPos::default() {
+        // The first move takes the piece out of the board
+        if lifted.is_none() {
+            let placed = board.is_placed(*id);
+            commands
+                .entity(entity)
+                .insert(Lifted {
+                    start: *grid_transform,
+                    start_placed: placed,
+                })
+                .remove::<Placed>();
+            board.remove(*id);
+            if placed {
+                score.moves += 1;
+            }
+            history.record(Action {
+                kind: if placed {
+                    ActionKind::Remove
+                } else {
+                    ActionKind::Pick
+                },
+                piece: *id,
+                before: PieceState::new(*grid_transform, placed),
+                after: PieceState::new(*grid_transform, false),
+            });
+        }
+        let before = PieceState::new(*grid_transform, false);
+        grid_transform.translate(delta);
+        history.join(Action {
+            kind: ActionKind::Move,
+            piece: *id,
+            before,
+            after: PieceState::new(*grid_transform, false),
+        });
+    }
+
+    // Same as the mouse buttons, the piece turns around its middle
+    let rotate = actions.just_pressed(InputAction::Rotate);
+    let flip = actions.just_pressed(InputAction::Flip);
+    let flip_vertical = actions.just_pressed(InputAction::FlipVertical);
+    if rotate || flip || flip_vertical {
+        let before = PieceState::new(*grid_transform, board.is_placed(*id));
+        let kind = if rotate {
+            grid_transform.rotate(shape);
+            ActionKind::Rotate
+        } else {
+            if flip_vertical {
+                grid_transform.flip_vertical(shape);
+            } else {
+                grid_transform.flip_horizontal(shape);
+            }
+            ActionKind::Flip
+        };
+        score.moves += 1;
+        super::refit_in_board(
+            &mut commands,
+            board,
+            entity,
+            *id,
+            shape,
+            &mut grid_transform,
+        );
+        let after = PieceState::new(*grid_transform, board.is_placed(*id));
+        super::record_turn(&mut history, kind, *id, before, after, lifted.is_some());
+    }
+
+    let lifted = match lifted {
+        Some(lifted) => lifted,
+        None => return,
+    };
+    if actions.just_pressed(InputAction::Place) {
+        let positions = grid_transform.positions(shape);
+        let dropped = PieceState::new(*grid_transform, false);
+        match board.place(*id, &positions) {
+            PlacementResult::Placed { offset } => {
+                grid_transform.translate(offset);
+                commands.entity(entity).insert(Placed).remove::<Lifted>();
+                score.moves += 1;
+                history.join(Action {
+                    kind: ActionKind::Place,
+                    piece: *id,
+                    before: dropped,
+                    after: PieceState::new(*grid_transform, true),
+                });
+                history.drop_unchanged_step();
+            }
+            PlacementResult::OutOfBounds if is_beside_board(board, &windows, *mode, &positions) => {
+                commands.entity(entity).remove::<Lifted>();
+                history.drop_unchanged_step();
+            }
+            // The piece stays lifted until it is moved somewhere it fits
+            _ => {}
+        }
+    } else if actions.just_pressed(InputAction::Cancel) {
+        let dropped = PieceState::new(*grid_transform, false);
+        *grid_transform = lifted.start;
+        let back = PieceState::new(*grid_transform, false);
+        history.join(Action {
+            kind: ActionKind::Move,
+            piece: *id,
+            before: dropped,
+            after: back,
+        });
+        if !lifted.start_placed {
+            commands.entity(entity).remove::<Lifted>();
+        } else if let PlacementResult::Placed { offset } =
+            board.place(*id, &grid_transform.positions(shape))
+        {
+            grid_transform.translate(offset);
+            commands.entity(entity).insert(Placed).remove::<Lifted>();
+            history.join(Action {
+                kind: ActionKind::Place,
+                piece: *id,
+                before: back,
+                after: PieceState::new(*grid_transform, true),
+            });
+        }
+        // Otherwise another piece took its cells,
+        // it stays lifted until it is moved somewhere it fits
+        history.drop_unchanged_step();
+    }
+}
+
+fn clear_focus(mut focus: ResMut<Focus>) {
+    focus.0 = None;
+}
+
+// The highlight is rebuilt when the focus changes or the focused piece turns
+fn spawn_focus_cells(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut piece_materials: ResMut<PieceMaterials>,
+    focus: Res<Focus>,
+    cells: Query<Entity, With<FocusCell>>,
+    pieces: Query<(&PieceId, &PieceShape, &GridTransform)>,
+    changed: Query<&PieceId, Changed<GridTransform>>,
+) {
+    let focused_changed = changed.iter().any(|id| Some(*id) == focus.0);
+    if !focus.is_changed() && !focused_changed {
+        return;
+    }
+    for entity in cells.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let (shape, grid_transform) = match pieces.iter().find(|(id, ..)| Some(**id) == focus.0) {
+        Some((_, shape, grid_transform)) => (shape, grid_transform),
+        None => return,
+    };
+    let material = piece_materials.get(&mut materials, Color::rgb(0.1, 0.1, 0.1));
+    let size = (SQUARE_WIDTH + 2 * FOCUS_BORDER) as f32;
+    for position in grid_transform.positions(shape) {
+        commands
+            .spawn_bundle(SpriteBundle {
+                material: material.clone(),
+                sprite: Sprite::new(Vec2::new(size, size)),
+                ..Default::default()
+            })
+            .insert(FocusCell {
+                offset: position - grid_transform.anchor,
+            });
+    }
+}
+
+// The highlight follows the piece, dragged or not
+fn draw_focus_cells(
+    focus: Res<Focus>,
+    pieces: Query<(&PieceId, &Transform), Without<FocusCell>>,
+    mut cells: Query<(&FocusCell, &mut Transform)>,
+) {
+    let piece = match pieces.iter().find(|(id, _)| Some(**id) == focus.0) {
+        Some((_, transform)) => transform.translation,
+        None => return,
+    };
+    for (cell, mut transform) in cells.iter_mut() {
+        let translation = piece + cell.offset.to_world(FOCUS_Z);
+        if transform.translation != translation {
+            transform.translation = translation;
+        }
+    }
+}
@@ -1,4 +1,10 @@
 pub mod board;
+pub mod catalog;
+pub mod grid;
+pub mod keyboard;
+pub mod orientation;
+pub mod polyomino;
+pub mod world;
 
 mod corner;
 mod l;
@@ -10,115 +16,394 @@ mod z;
 
 extern crate t_triste_macro;
 
-use bevy::{math::vec3, prelude::*};
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    controls::{Actions, InputAction},
     cursor::Cursor,
-    piece::{corner::Corner, l::L, rectangle::Rectangle, square::Square, z::Z},
+    history::{Action, ActionKind, History, PieceState},
+    piece::{
+        board::{Board, PlacementResult},
+        catalog::Polyomino,
+        corner::Corner,
+        grid::GridPos,
+        l::L,
+        rectangle::Rectangle,
+        square::Square,
+        z::Z,
+    },
+    score::Score,
+    state::AppState,
+    versus::Player,
+};
+use keyboard::Lifted;
+use piece::Position;
+pub use piece::{
+    cell_under, Dragging, GridTransform, Piece, PieceBundle, PieceColor, PieceShape, Placed,
 };
-use piece::{Piece, Position};
+use world::WorldPos;
 
 pub const SQUARE_WIDTH: i32 = 50;
 
+const PIECE_Z: f32 = 1.;
+const DRAGGED_PIECE_Z: f32 = 1.5;
+
 // Plugins
 pub struct PiecePlugin;
-pub struct GameState(pub Vec<Box<dyn Piece>>);
+
+// Components
+// Index of a piece in its level, the board and the solver know pieces by it.
+// In a race the pieces of player two follow the ones of player one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PieceId(pub usize);
+
+// The pieces a level can be made of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PieceKind {
+    Rectangle,
+    L,
+    Z,
+    Corner,
+    Square,
+    // Any piece of the catalog
+    Polyomino(Polyomino),
+}
+
+impl PieceKind {
+    // Pieces are created from grid cells, not from pixels.
+    pub fn build(self, position: GridPos) -> Box<dyn Piece> {
+        match self {
+            PieceKind::Rectangle => Box::new(Rectangle::new(position.col, position.row)),
+            PieceKind::L => Box::new(L::new(position.col, position.row)),
+            PieceKind::Z => Box::new(Z::new(position.col, position.row)),
+            PieceKind::Corner => Box::new(Corner::new(position.col, position.row)),
+            PieceKind::Square => Box::new(Square::new(position.col, position.row)),
+            PieceKind::Polyomino(polyomino) => polyomino.build(position),
+        }
+    }
+}
+
+// Resources
+// One material per color, shared by the squares of every piece
+#[derive(Default)]
+pub struct PieceMaterials(HashMap<[u32; 4], Handle<ColorMaterial>>);
+
+impl PieceMaterials {
+    pub fn get(
+        &mut self,
+        materials: &mut Assets<ColorMaterial>,
+        color: Color,
+    ) -> Handle<ColorMaterial> {
+        let [red, green, blue, alpha] = color.as_rgba_f32();
+        let key = [
+            red.to_bits(),
+            green.to_bits(),
+            blue.to_bits(),
+            alpha.to_bits(),
+        ];
+        self.0
+            .entry(key)
+            .or_insert_with(|| materials.add(color.into()))
+            .clone()
+    }
+}
 
 impl Plugin for PiecePlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.insert_non_send_resource(GameState(vec![
-            Box::new(Rectangle::new(100, 100)),
-            Box::new(L::new(200, 300)),
-            Box::new(Z::new(400, 500)),
-            Box::new(Corner::new(100, 300)),
-            Box::new(Square::new(300, 100)),
-        ]))
-        .add_system_to_stage(CoreStage::PreUpdate, clear.system())
-        .add_system(release_piece.system())
-        .add_system(click_piece.system())
-        .add_system(move_piece.system())
-        .add_system(draw_piece.system());
+        // Pieces themselves are spawned by the level
+        app.insert_resource(PieceMaterials::default())
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(release_piece.system().label("release_piece"))
+                    .with_system(click_piece.system())
+                    .with_system(move_piece.system()),
+            )
+            .add_system(draw_piece.system())
+            .add_system(draw_squares.system());
     }
 }
 
+// Spawns a piece entity, its children are the squares drawing it
+pub fn spawn_piece(
+    commands: &mut Commands,
+    material: Handle<ColorMaterial>,
+    bundle: PieceBundle,
+) -> Entity {
+    let anchor = bundle.grid_transform.anchor;
+    let positions = bundle.positions();
+    commands
+        .spawn_bundle(bundle)
+        .insert(Transform::from_translation(anchor.to_world(PIECE_Z)))
+        .insert(GlobalTransform::default())
+        .with_children(|parent| {
+            for position in positions.iter() {
+                parent
+                    .spawn_bundle(SpriteBundle {
+                        material: material.clone(),
+                        sprite: Sprite::new(Vec2::new(
+                            (SQUARE_WIDTH - 1) as f32,
+                            (SQUARE_WIDTH - 1) as f32,
+                        )),
+                        transform: Transform::from_translation((*position - anchor).to_world(0.)),
+                        ..Default::default()
+                    })
+                    .insert(Position);
+            }
+        })
+        .id()
+}
+
 // Systems
-fn clear(mut commands: Commands, query: Query<Entity, With<Position>>) {
-    for entity in query.iter() {
-        commands.entity(entity).despawn();
+#[allow(clippy::type_complexity)]
+fn draw_piece(
+    cursor: Res<Cursor>,
+    mut pieces: Query<
+        (
+            &GridTransform,
+            Option<&Dragging>,
+            Option<&Lifted>,
+            &mut Transform,
+        ),
+        Without<Position>,
+    >,
+) {
+    for (grid_transform, dragging, lifted, mut transform) in pieces.iter_mut() {
+        // A dragged piece follows the cursor smoothly and is drawn over the others
+        let translation = match (dragging, lifted) {
+            (Some(dragging), _) => {
+                grid_transform.anchor.to_world(DRAGGED_PIECE_Z)
+                    + dragging.drag_offset(cursor.current_pos).extend(0.)
+            }
+            (None, Some(_)) => grid_transform.anchor.to_world(DRAGGED_PIECE_Z),
+            (None, None) => grid_transform.anchor.to_world(PIECE_Z),
+        };
+        // Transforms are only written when the piece moved
+        if transform.translation != translation {
+            transform.translation = translation;
+        }
     }
 }
 
-fn draw_piece(
-    mut commands: Commands,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    mut game_state: NonSendMut<GameState>,
+// Squares only move when their piece turned or moved on the grid
+fn draw_squares(
+    pieces: Query<(&PieceShape, &GridTransform, &Children), Changed<GridTransform>>,
+    mut squares: Query<&mut Transform, With<Position>>,
 ) {
-    for piece in game_state.0.iter_mut() {
-        let material = materials.add(piece.color().into());
-        let positions = piece.positions();
-        for position in positions.iter() {
-            commands
-                .spawn_bundle(SpriteBundle {
-                    material: material.clone(),
-                    sprite: Sprite::new(Vec2::new(
-                        (SQUARE_WIDTH - 1) as f32,
-                        (SQUARE_WIDTH - 1) as f32,
-                    )),
-                    transform: Transform::from_translation(vec3(
-                        position.x, position.y, position.z,
-                    )),
-                    ..Default::default()
-                })
-                .insert(Position);
+    for (shape, grid_transform, children) in pieces.iter() {
+        let positions = grid_transform.positions(shape);
+        for (child, position) in children.iter().zip(positions.iter()) {
+            if let Ok(mut square) = squares.get_mut(*child) {
+                square.translation = (*position - grid_transform.anchor).to_world(0.);
+            }
         }
     }
 }
 
-fn move_piece(cursor: Res<Cursor>, mut game_state: NonSendMut<GameState>) {
+fn move_piece(
+    cursor: Res<Cursor>,
+    mut history: ResMut<History>,
+    mut pieces: Query<(&PieceId, &mut Dragging, &mut GridTransform)>,
+) {
     if cursor.is_pressed {
-        game_state
-            .0
-            .iter_mut()
-            .filter(|piece| piece.is_moving())
-            .for_each(|piece| {
-                piece.move_it(&cursor);
-            })
+        for (id, mut dragging, mut grid_transform) in pieces.iter_mut() {
+            // Components are only touched when the piece changes cell
+            let target = dragging.target(cursor.current_pos);
+            if target != dragging.cell {
+                let before = PieceState::new(*grid_transform, false);
+                grid_transform.translate(target - dragging.cell);
+                dragging.cell = target;
+                history.join(Action {
+                    kind: ActionKind::Move,
+                    piece: *id,
+                    before,
+                    after: PieceState::new(*grid_transform, false),
+                });
+            }
+        }
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn click_piece(
+    mut commands: Commands,
     cursor: Res<Cursor>,
-    mouse_button_input: Res<Input<MouseButton>>,
-    mut game_state: NonSendMut<GameState>,
+    actions: Res<Actions>,
+    mut board: ResMut<Board>,
+    mut score: ResMut<Score>,
+    mut history: ResMut<History>,
+    mut pieces: Query<(
+        Entity,
+        &PieceId,
+        &PieceShape,
+        &mut GridTransform,
+        Option<&Dragging>,
+        &Player,
+    )>,
 ) {
-    if mouse_button_input.just_pressed(MouseButton::Left) {
-        for piece in game_state.0.iter_mut() {
-            if piece.is_even_odd(cursor.current_pos) {
-                piece.set_moving(true);
+    if actions.just_pressed(InputAction::PointerGrab) {
+        for (entity, id, shape, grid_transform, dragging, player) in pieces.iter_mut() {
+            // The pieces of player two only answer to the keyboard
+            if *player != Player::One {
+                continue;
+            }
+            if grid_transform
+                .positions(shape)
+                .contains(&cell_under(dragging, cursor.current_pos))
+            {
+                let placed = board.is_placed(*id);
+                commands
+                    .entity(entity)
+                    .insert(Dragging::new(*grid_transform, cursor.current_pos, placed))
+                    .remove::<Placed>()
+                    .remove::<Lifted>();
+                // The piece is taken out of the board
+                board.remove(*id);
+                if placed {
+                    score.moves += 1;
+                }
+                history.record(Action {
+                    kind: if placed {
+                        ActionKind::Remove
+                    } else {
+                        ActionKind::Pick
+                    },
+                    piece: *id,
+                    before: PieceState::new(*grid_transform, placed),
+                    after: PieceState::new(*grid_transform, false),
+                });
                 return;
             }
         }
     }
-    if mouse_button_input.just_pressed(MouseButton::Right) {
-        for piece in game_state.0.iter_mut() {
-            if piece.is_even_odd(cursor.current_pos) {
-                piece.rotate();
+    if actions.just_pressed(InputAction::PointerRotate) {
+        for (entity, id, shape, mut grid_transform, dragging, player) in pieces.iter_mut() {
+            // The pieces of player two only answer to the keyboard
+            if *player != Player::One {
+                continue;
             }
+            let pivot = cell_under(dragging, cursor.current_pos);
+            if grid_transform.positions(shape).contains(&pivot) {
+                let before = PieceState::new(*grid_transform, board.is_placed(*id));
+                // Turn around the grabbed cell so it stays under the cursor
+                grid_transform.rotate_around(pivot);
+                score.moves += 1;
+                refit_in_board(
+                    &mut commands,
+                    &mut board,
+                    entity,
+                    *id,
+                    shape,
+                    &mut grid_transform,
+                );
+                let after = PieceState::new(*grid_transform, board.is_placed(*id));
+                record_turn(
+                    &mut history,
+                    ActionKind::Rotate,
+                    *id,
+                    before,
+                    after,
+                    dragging.is_some(),
+                );
+            }
+        }
+    }
+    // Middle click flips horizontally, vertically with shift
+    let vertical = actions.just_pressed(InputAction::PointerFlipVertical);
+    if vertical || actions.just_pressed(InputAction::PointerFlip) {
+        for (entity, id, shape, mut grid_transform, dragging, player) in pieces.iter_mut() {
+            // The pieces of player two only answer to the keyboard
+            if *player != Player::One {
+                continue;
+            }
+            if grid_transform
+                .positions(shape)
+                .contains(&cell_under(dragging, cursor.current_pos))
+            {
+                let before = PieceState::new(*grid_transform, board.is_placed(*id));
+                if vertical {
+                    grid_transform.flip_vertical(shape);
+                } else {
+                    grid_transform.flip_horizontal(shape);
+                }
+                score.moves += 1;
+                refit_in_board(
+                    &mut commands,
+                    &mut board,
+                    entity,
+                    *id,
+                    shape,
+                    &mut grid_transform,
+                );
+                let after = PieceState::new(*grid_transform, board.is_placed(*id));
+                record_turn(
+                    &mut history,
+                    ActionKind::Flip,
+                    *id,
+                    before,
+                    after,
+                    dragging.is_some(),
+                );
+            }
+        }
+    }
+}
+
+// A piece turned while dragged or lifted is part of the move step
+fn record_turn(
+    history: &mut History,
+    kind: ActionKind,
+    piece: PieceId,
+    before: PieceState,
+    after: PieceState,
+    moving: bool,
+) {
+    let action = Action {
+        kind,
+        piece,
+        before,
+        after,
+    };
+    if moving {
+        history.join(action);
+    } else {
+        history.record(action);
+    }
+}
+
+// A placed piece that changed its shape stays in the board only if it still fits
+fn refit_in_board(
+    commands: &mut Commands,
+    board: &mut Board,
+    entity: Entity,
+    id: PieceId,
+    shape: &PieceShape,
+    grid_transform: &mut GridTransform,
+) {
+    if !board.is_placed(id) {
+        return;
+    }
+    match board.place(id, &grid_transform.positions(shape)) {
+        PlacementResult::Placed { offset } => grid_transform.translate(offset),
+        _ => {
+            board.remove(id);
+            commands.entity(entity).remove::<Placed>();
         }
     }
 }
 
 fn release_piece(
-    mouse_button_input: Res<Input<MouseButton>>,
-    mut game_state: NonSendMut<GameState>,
+    mut commands: Commands,
+    actions: Res<Actions>,
+    pieces: Query<Entity, With<Dragging>>,
 ) {
-    if !mouse_button_input.just_released(MouseButton::Left) {
+    if !actions.just_released(InputAction::PointerGrab) {
         return;
     }
 
-    game_state
-        .0
-        .iter_mut()
-        .filter(|piece| piece.is_moving())
-        .for_each(|piece| piece.set_moving(false));
+    for entity in pieces.iter() {
+        commands.entity(entity).remove::<Dragging>();
+    }
 }
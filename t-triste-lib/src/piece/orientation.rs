@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+
+use crate::piece::grid::GridPos;
+
+// Counterclockwise rotation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Rotation {
+    R0,
+    R90,
+    R180,
+    R270,
+}
+
+// `#[default]` on an enum variant needs a newer compiler than Bevy 0.5 does
+#[allow(clippy::derivable_impls)]
+impl Default for Rotation {
+    fn default() -> Self {
+        Rotation::R0
+    }
+}
+
+impl Rotation {
+    pub fn from_quarter_turns(quarter_turns: u8) -> Self {
+        match quarter_turns % 4 {
+            0 => Rotation::R0,
+            1 => Rotation::R90,
+            2 => Rotation::R180,
+            _ => Rotation::R270,
+        }
+    }
+
+    pub fn quarter_turns(self) -> u8 {
+        match self {
+            Rotation::R0 => 0,
+            Rotation::R90 => 1,
+            Rotation::R180 => 2,
+            Rotation::R270 => 3,
+        }
+    }
+
+    pub fn degrees(self) -> u16 {
+        self.quarter_turns() as u16 * 90
+    }
+}
+
+// How a piece is turned compared to the shape it was built with.
+// The shape is mirrored first (along the vertical axis), then rotated.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct Orientation {
+    #[serde(default)]
+    pub rotation: Rotation,
+    #[serde(default)]
+    pub mirrored: bool,
+}
+
+impl Orientation {
+    pub const fn new(rotation: Rotation, mirrored: bool) -> Self {
+        Orientation { rotation, mirrored }
+    }
+
+    // The 8 orientations of a piece
+    pub fn all() -> Vec<Orientation> {
+        let mut orientations = vec![];
+        for mirrored in [false, true].iter() {
+            for quarter_turns in 0..4 {
+                orientations.push(Orientation::new(
+                    Rotation::from_quarter_turns(quarter_turns),
+                    *mirrored,
+                ));
+            }
+        }
+        orientations
+    }
+
+    // Moves a cell of the shape, the shape origin stays in place
+    pub fn apply(self, cell: GridPos) -> GridPos {
+        let mut cell = cell;
+        if self.mirrored {
+            cell.col = -cell.col;
+        }
+        for _ in 0..self.rotation.quarter_turns() {
+            cell = cell.rotate_around(GridPos::default());
+        }
+        cell
+    }
+
+    pub fn inverse(self) -> Self {
+        if self.mirrored {
+            // A mirror then a rotation is its own inverse
+            self
+        } else {
+            Orientation::new(
+                Rotation::from_quarter_turns(4 - self.rotation.quarter_turns()),
+                false,
+            )
+        }
+    }
+
+    pub fn rotated(self) -> Self {
+        Orientation::new(
+            Rotation::from_quarter_turns(self.rotation.quarter_turns() + 1),
+            self.mirrored,
+        )
+    }
+
+    // Mirroring a rotated shape turns it the other way around
+    pub fn flipped_horizontal(self) -> Self {
+        Orientation::new(
+            Rotation::from_quarter_turns(4 - self.rotation.quarter_turns()),
+            !self.mirrored,
+        )
+    }
+
+    // A vertical flip is an horizontal flip followed by a half turn
+    pub fn flipped_vertical(self) -> Self {
+        Orientation::new(
+            Rotation::from_quarter_turns(6 - self.rotation.quarter_turns()),
+            !self.mirrored,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An asymmetric shape, any mistake moves one of its cells
+    fn cells() -> Vec<GridPos> {
+        vec![
+            GridPos::new(0, 0),
+            GridPos::new(1, 0),
+            GridPos::new(0, 1),
+            GridPos::new(0, 2),
+            GridPos::new(-1, 2),
+            GridPos::new(3, -2),
+        ]
+    }
+
+    #[test]
+    fn all_orientations_are_distinct() {
+        let mut orientations = Orientation::all();
+        assert_eq!(orientations[0], Orientation::default());
+        orientations.sort();
+        orientations.dedup();
+        assert_eq!(orientations.len(), 8);
+    }
+
+    #[test]
+    fn rotation_quarter_turns() {
+        for quarter_turns in 0..8 {
+            let rotation = Rotation::from_quarter_turns(quarter_turns);
+            assert_eq!(rotation.quarter_turns(), quarter_turns % 4);
+        }
+        assert_eq!(Rotation::default(), Rotation::R0);
+        assert_eq!(Rotation::R270.degrees(), 270);
+    }
+
+    #[test]
+    fn apply_mirrors_then_rotates() {
+        let cell = GridPos::new(2, 1);
+        let apply = |rotation, mirrored| Orientation::new(rotation, mirrored).apply(cell);
+        assert_eq!(apply(Rotation::R0, false), cell);
+        assert_eq!(apply(Rotation::R90, false), GridPos::new(-1, 2));
+        assert_eq!(apply(Rotation::R180, false), GridPos::new(-2, -1));
+        assert_eq!(apply(Rotation::R0, true), GridPos::new(-2, 1));
+        assert_eq!(apply(Rotation::R90, true), GridPos::new(-1, -2));
+    }
+
+    #[test]
+    fn inverse_undoes_apply() {
+        for orientation in Orientation::all() {
+            for cell in cells() {
+                assert_eq!(orientation.inverse().apply(orientation.apply(cell)), cell);
+                assert_eq!(orientation.apply(orientation.inverse().apply(cell)), cell);
+            }
+        }
+    }
+
+    #[test]
+    fn rotated_adds_a_quarter_turn() {
+        for orientation in Orientation::all() {
+            for cell in cells() {
+                assert_eq!(
+                    orientation.rotated().apply(cell),
+                    orientation.apply(cell).rotate_around(GridPos::default())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn flips_mirror_the_applied_cells() {
+        for orientation in Orientation::all() {
+            for cell in cells() {
+                let applied = orientation.apply(cell);
+                assert_eq!(
+                    orientation.flipped_horizontal().apply(cell),
+                    GridPos::new(-applied.col, applied.row)
+                );
+                assert_eq!(
+                    orientation.flipped_vertical().apply(cell),
+                    GridPos::new(applied.col, -applied.row)
+                );
+            }
+            // Flipping twice goes back to the start
+            assert_eq!(
+                orientation.flipped_horizontal().flipped_horizontal(),
+                orientation
+            );
+            assert_eq!(
+                orientation.flipped_vertical().flipped_vertical(),
+                orientation
+            );
+        }
+    }
+}
@@ -1,81 +1,302 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::cursor::Cursor;
-
-use crate::SQUARE_WIDTH;
+use crate::{
+    piece::{
+        grid::{centroid_cell, GridPos},
+        orientation::Orientation,
+        world::WorldPos,
+        PieceId,
+    },
+    versus::Player,
+};
 
 // Components
+// Marker of the squares drawing a piece
 pub struct Position;
 
+// Cells of a piece before it is moved or turned
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PieceShape(pub Vec<GridPos>);
+
+// Where a piece lies on the grid and how it is turned
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GridTransform {
+    // The cell of the shape origin
+    pub anchor: GridPos,
+    pub orientation: Orientation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PieceColor(pub Color);
+
+// A piece following the cursor, it moves rigidly with the grabbed cell
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dragging {
+    // The grabbed cell, it stays in place when the piece turns
+    pub cell: GridPos,
+    // Cursor position from the center of the grabbed cell
+    pub offset: Vec2,
+    // Where the piece was picked up, to put it back after a bad drop
+    pub start: GridTransform,
+    pub start_placed: bool,
+}
+
+// Marker of the pieces lying on the board
+pub struct Placed;
+
+#[derive(Bundle)]
+pub struct PieceBundle {
+    pub id: PieceId,
+    pub shape: PieceShape,
+    pub grid_transform: GridTransform,
+    pub color: PieceColor,
+    // The side the piece is played on, player one in solo
+    pub player: Player,
+}
+
+// A kind of piece and where it starts, see `#[derive(PieceBehavior)]`
 pub trait Piece {
-    fn positions(&self) -> Vec<Vec3>;
+    fn shape(&self) -> Vec<GridPos>;
     fn color(&self) -> Color;
-    fn rotate(&mut self);
-    fn snap(&mut self);
-    fn move_it(&mut self, cursor: &Res<Cursor>);
-    fn set_moving(&mut self, moving: bool);
-    fn is_moving(&self) -> bool;
-
-    fn is_even_odd(&self, current_pos: Vec2) -> bool {
-        self.positions().iter().any(|piece_pos| {
-            piece_pos.x - (SQUARE_WIDTH / 2) as f32 <= current_pos.x
-                && current_pos.x <= piece_pos.x + (SQUARE_WIDTH / 2) as f32
-                && piece_pos.y - (SQUARE_WIDTH / 2) as f32 <= current_pos.y
-                && current_pos.y <= piece_pos.y + (SQUARE_WIDTH / 2) as f32
-        })
+    fn anchor(&self) -> GridPos;
+
+    fn bundle(&self, id: PieceId) -> PieceBundle {
+        PieceBundle {
+            id,
+            shape: PieceShape(self.shape()),
+            grid_transform: GridTransform::new(self.anchor()),
+            color: PieceColor(self.color()),
+            player: Player::One,
+        }
+    }
+}
+
+impl PieceBundle {
+    pub fn positions(&self) -> Vec<GridPos> {
+        self.grid_transform.positions(&self.shape)
+    }
+}
+
+impl GridTransform {
+    pub fn new(anchor: GridPos) -> Self {
+        GridTransform {
+            anchor,
+            orientation: Orientation::default(),
+        }
+    }
+
+    pub fn positions(&self, shape: &PieceShape) -> Vec<GridPos> {
+        shape
+            .0
+            .iter()
+            .map(|cell| self.orientation.apply(*cell) + self.anchor)
+            .collect()
+    }
+
+    pub fn translate(&mut self, delta: GridPos) {
+        self.anchor = self.anchor + delta;
+    }
+
+    // Turns the piece, the `pivot` cell stays in place
+    pub fn reorient(&mut self, orientation: Orientation, pivot: GridPos) {
+        // Where the pivot is in the shape, then where it goes with the new orientation
+        let shape_pivot = self.orientation.inverse().apply(pivot - self.anchor);
+        self.anchor = pivot - orientation.apply(shape_pivot);
+        self.orientation = orientation;
+    }
+
+    // The cell nearest to the middle of the piece, it stays in place when turning
+    pub fn center(&self, shape: &PieceShape) -> GridPos {
+        centroid_cell(&self.positions(shape))
+    }
+
+    pub fn set_orientation(&mut self, shape: &PieceShape, orientation: Orientation) {
+        let pivot = self.center(shape);
+        self.reorient(orientation, pivot);
+    }
+
+    pub fn rotate(&mut self, shape: &PieceShape) {
+        let pivot = self.center(shape);
+        self.rotate_around(pivot);
+    }
+
+    pub fn rotate_around(&mut self, pivot: GridPos) {
+        self.reorient(self.orientation.rotated(), pivot);
+    }
+
+    pub fn flip_horizontal(&mut self, shape: &PieceShape) {
+        self.set_orientation(shape, self.orientation.flipped_horizontal());
+    }
+
+    pub fn flip_vertical(&mut self, shape: &PieceShape) {
+        self.set_orientation(shape, self.orientation.flipped_vertical());
+    }
+}
+
+impl Dragging {
+    pub fn new(grid_transform: GridTransform, cursor_pos: Vec2, placed: bool) -> Self {
+        let cell = GridPos::at_world(cursor_pos);
+        Dragging {
+            cell,
+            offset: cursor_pos - cell.to_world(0.).truncate(),
+            start: grid_transform,
+            start_placed: placed,
+        }
+    }
+
+    // Where the grabbed cell goes, under the cursor
+    pub fn target(&self, cursor_pos: Vec2) -> GridPos {
+        GridPos::at_world(cursor_pos - self.offset)
+    }
+
+    // Pixels between the cells of the piece and where it is drawn
+    pub fn drag_offset(&self, cursor_pos: Vec2) -> Vec2 {
+        cursor_pos - self.offset - self.cell.to_world(0.).truncate()
+    }
+
+    pub fn put_back(&self, grid_transform: &mut GridTransform) {
+        *grid_transform = self.start;
+    }
+}
+
+// The cell of a piece drawn under the cursor
+pub fn cell_under(dragging: Option<&Dragging>, cursor_pos: Vec2) -> GridPos {
+    match dragging {
+        // A dragged piece is drawn away from its cells, the cursor stays on the grabbed one
+        Some(dragging) => dragging.cell,
+        None => GridPos::at_world(cursor_pos),
     }
 }
 
-// #[cfg(test)]
-// mod tests {
-//     // use super::*;
-//     // #[test]
-//     // fn test_even_odd_ko() {
-//     //     // Given
-//     //     let piece_pos = Vec3::new(1.0, 1.0, 1.0);
-//     //     let current_pos = Vec2::new(60., 40.);
-//     //     // When
-//     //     let result = Piece::is_even_odd(piece_pos, current_pos);
-//     //     // Then
-//     //     assert_eq!(result, false);
-//     // }
-
-//     // #[test]
-//     // fn test_even_odd_same_position() {
-//     //     // Given
-//     //     let piece_pos = Vec3::new(1.0, 1.0, 1.0);
-//     //     let current_pos = Vec2::new(1., 1.);
-
-//     //     // When
-//     //     let result = Piece::is_even_odd(piece_pos, current_pos);
-
-//     //     // Then
-//     //     assert_eq!(result, true);
-//     // }
-
-//     // #[test]
-//     // fn test_even_odd_ok_different_position_in_area() {
-//     //     // Given
-//     //     let piece_pos = Vec3::new(1.0, 1.0, 1.0);
-//     //     let current_pos = Vec2::new(5., 10.);
-
-//     //     // When
-//     //     let result = Piece::is_even_odd(piece_pos, current_pos);
-
-//     //     // Then
-//     //     assert_eq!(result, true);
-//     // }
-
-//     #[test]
-//     fn test_even_odd_ok_left_side_in_area() {
-//         // Given
-//         let piece_pos = Vec3::new(10.0, 10.0, 1.0);
-//         let current_pos = Vec2::new(5., 5.);
-
-//         // When
-//         let result = Piece::is_even_odd(piece_pos, current_pos);
-
-//         // Then
-//         assert_eq!(result, true);
-//     }
-// }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_outside_the_cell() {
+        // Given
+        let cell = GridPos::new(1, 1);
+        let cursor_pos = Vec2::new(100., 120.);
+
+        // When
+        let result = cell.contains_world(cursor_pos);
+
+        // Then
+        assert!(!result);
+        assert_eq!(GridPos::at_world(cursor_pos), GridPos::new(2, 2));
+    }
+
+    #[test]
+    fn cursor_on_the_cell_center() {
+        // Given
+        let cell = GridPos::new(1, 1);
+        let cursor_pos = Vec2::new(50., 50.);
+
+        // When
+        let result = cell.contains_world(cursor_pos);
+
+        // Then
+        assert!(result);
+        assert_eq!(GridPos::at_world(cursor_pos), cell);
+    }
+
+    #[test]
+    fn cursor_in_the_cell_area() {
+        // Given
+        let cell = GridPos::new(1, 1);
+        let cursor_pos = Vec2::new(30., 70.);
+
+        // When
+        let result = cell.contains_world(cursor_pos);
+
+        // Then
+        assert!(result);
+        assert_eq!(GridPos::at_world(cursor_pos), cell);
+    }
+
+    #[test]
+    fn drag_keeps_the_grab_offset() {
+        // Given
+        let grid_transform = GridTransform::new(GridPos::new(2, 2));
+        let dragging = Dragging::new(grid_transform, Vec2::new(110., 95.), true);
+
+        // When
+        let target = dragging.target(Vec2::new(160., 195.));
+
+        // Then
+        assert_eq!(dragging.cell, GridPos::new(2, 2));
+        assert_eq!(dragging.offset, Vec2::new(10., -5.));
+        assert_eq!(target, GridPos::new(3, 4));
+        assert_eq!(
+            dragging.drag_offset(Vec2::new(160., 195.)),
+            Vec2::new(50., 100.)
+        );
+        assert_eq!(
+            cell_under(Some(&dragging), Vec2::new(0., 0.)),
+            GridPos::new(2, 2)
+        );
+    }
+
+    fn l_shape() -> PieceShape {
+        PieceShape(vec![
+            GridPos::new(0, 0),
+            GridPos::new(0, 1),
+            GridPos::new(0, 2),
+            GridPos::new(1, 0),
+        ])
+    }
+
+    #[test]
+    fn reorient_keeps_the_pivot_in_place() {
+        let shape = l_shape();
+        let pivot = GridPos::new(5, 6);
+        for orientation in Orientation::all() {
+            let mut grid_transform = GridTransform::new(GridPos::new(5, 4));
+            grid_transform.reorient(orientation, pivot);
+            assert_eq!(grid_transform.orientation, orientation);
+            assert!(grid_transform.positions(&shape).contains(&pivot));
+            // Going back puts every cell back
+            grid_transform.reorient(Orientation::default(), pivot);
+            assert_eq!(grid_transform, GridTransform::new(GridPos::new(5, 4)));
+        }
+    }
+
+    #[test]
+    fn set_orientation_turns_around_the_center() {
+        let shape = l_shape();
+        for orientation in Orientation::all() {
+            let mut grid_transform = GridTransform::new(GridPos::new(3, 3));
+            let center = grid_transform.center(&shape);
+            grid_transform.set_orientation(&shape, orientation);
+            assert_eq!(grid_transform.orientation, orientation);
+            assert!(grid_transform.positions(&shape).contains(&center));
+            let mut cells: Vec<GridPos> = shape
+                .0
+                .iter()
+                .map(|cell| orientation.apply(*cell) + grid_transform.anchor)
+                .collect();
+            cells.sort();
+            let mut positions = grid_transform.positions(&shape);
+            positions.sort();
+            assert_eq!(positions, cells);
+        }
+    }
+
+    #[test]
+    fn four_rotations_go_back_to_the_start() {
+        let shape = l_shape();
+        let start = GridTransform::new(GridPos::new(3, 3));
+        let mut grid_transform = start;
+        for _ in 0..4 {
+            grid_transform.rotate(&shape);
+        }
+        assert_eq!(grid_transform, start);
+        grid_transform.flip_horizontal(&shape);
+        grid_transform.flip_horizontal(&shape);
+        assert_eq!(grid_transform, start);
+        grid_transform.flip_vertical(&shape);
+        grid_transform.flip_vertical(&shape);
+        assert_eq!(grid_transform, start);
+    }
+}
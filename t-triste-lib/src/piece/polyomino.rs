@@ -0,0 +1,234 @@
+use std::collections::HashSet;
+
+use crate::piece::{
+    grid::GridPos,
+    orientation::{Orientation, Rotation},
+};
+
+// Cell sets of pieces, as built by `PieceBuilder` or parsed from a shape.
+// A fixed polyomino cannot be turned, a one sided one can only be rotated
+// and a free one can also be mirrored.
+
+// The symmetries a shape keeps, named after the transformations that leave it unchanged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Symmetry {
+    // No symmetry, like the F pentomino
+    None,
+    // Only the half turn, like the S tetromino
+    HalfTurn,
+    // Only the quarter turns
+    QuarterTurn,
+    // A vertical or an horizontal mirror, like the T tetromino
+    AxisMirror,
+    // A diagonal mirror, like the V pentomino
+    DiagonalMirror,
+    // Both vertical and horizontal mirrors, like the I tetromino
+    AxisMirrors,
+    // Both diagonal mirrors
+    DiagonalMirrors,
+    // Every rotation and mirror, like the X pentomino
+    Full,
+}
+
+// The corner of the smallest rectangle around the cells
+pub fn bottom_left(cells: &[GridPos]) -> GridPos {
+    GridPos::new(
+        cells.iter().map(|cell| cell.col).min().unwrap_or(0),
+        cells.iter().map(|cell| cell.row).min().unwrap_or(0),
+    )
+}
+
+// Sorts the cells and moves them so their bottom left corner is at (0, 0)
+pub fn normalize(cells: &[GridPos]) -> Vec<GridPos> {
+    let corner = bottom_left(cells);
+    let mut cells: Vec<GridPos> = cells.iter().map(|cell| *cell - corner).collect();
+    cells.sort();
+    cells
+}
+
+// The same cells for every rotation of a shape
+pub fn canonical_one_sided(cells: &[GridPos]) -> Vec<GridPos> {
+    canonical_among(
+        cells,
+        Orientation::all().into_iter().filter(|o| !o.mirrored),
+    )
+}
+
+// The same cells for every rotation and mirror of a shape
+pub fn canonical(cells: &[GridPos]) -> Vec<GridPos> {
+    canonical_among(cells, Orientation::all().into_iter())
+}
+
+fn canonical_among<I: Iterator<Item = Orientation>>(
+    cells: &[GridPos],
+    orientations: I,
+) -> Vec<GridPos> {
+    orientations
+        .map(|orientation| turn(cells, orientation))
+        .min()
+        .unwrap_or_default()
+}
+
+fn turn(cells: &[GridPos], orientation: Orientation) -> Vec<GridPos> {
+    let turned: Vec<GridPos> = cells.iter().map(|cell| orientation.apply(*cell)).collect();
+    normalize(&turned)
+}
+
+// The orientations leaving the shape unchanged, `Orientation::default()` included
+pub fn symmetries(cells: &[GridPos]) -> Vec<Orientation> {
+    let shape = normalize(cells);
+    Orientation::all()
+        .into_iter()
+        .filter(|orientation| turn(&shape, *orientation) == shape)
+        .collect()
+}
+
+// How many different ways the shape can be put down, from 1 to 8
+pub fn distinct_orientations(cells: &[GridPos]) -> usize {
+    Orientation::all().len() / symmetries(cells).len()
+}
+
+pub fn symmetry(cells: &[GridPos]) -> Symmetry {
+    let symmetries = symmetries(cells);
+    let rotations = symmetries.iter().filter(|o| !o.mirrored).count();
+    // Mirrored with a half turn or none, the mirror is vertical or horizontal
+    let axis_mirror = symmetries
+        .iter()
+        .any(|o| o.mirrored && (o.rotation == Rotation::R0 || o.rotation == Rotation::R180));
+    match (rotations, symmetries.len() - rotations) {
+        (1, 0) => Symmetry::None,
+        (2, 0) => Symmetry::HalfTurn,
+        (4, 0) => Symmetry::QuarterTurn,
+        (1, _) if axis_mirror => Symmetry::AxisMirror,
+        (1, _) => Symmetry::DiagonalMirror,
+        (2, _) if axis_mirror => Symmetry::AxisMirrors,
+        (2, _) => Symmetry::DiagonalMirrors,
+        _ => Symmetry::Full,
+    }
+}
+
+// Every polyomino of `order` cells, each translation listed once.
+// Redelmeier's algorithm grows them from (0, 0), only adding cells above
+// or on the right of it so no polyomino is built twice.
+pub fn fixed_polyominoes(order: usize) -> Vec<Vec<GridPos>> {
+    let mut polyominoes = vec![];
+    if order == 0 {
+        return polyominoes;
+    }
+    let origin = GridPos::default();
+    let mut seen = HashSet::new();
+    seen.insert(origin);
+    grow(
+        order,
+        &mut vec![],
+        vec![origin],
+        &mut seen,
+        &mut polyominoes,
+    );
+    polyominoes
+}
+
+fn grow(
+    order: usize,
+    polyomino: &mut Vec<GridPos>,
+    mut untried: Vec<GridPos>,
+    seen: &mut HashSet<GridPos>,
+    polyominoes: &mut Vec<Vec<GridPos>>,
+) {
+    while let Some(cell) = untried.pop() {
+        polyomino.push(cell);
+        if polyomino.len() == order {
+            polyominoes.push(normalize(polyomino));
+        } else {
+            // Neighbours of the new cell that no smaller polyomino could reach
+            let added: Vec<GridPos> = [(1, 0), (0, 1), (-1, 0), (0, -1)]
+                .iter()
+                .map(|(col, row)| cell + GridPos::new(*col, *row))
+                .filter(|next| next.row > 0 || (next.row == 0 && next.col >= 0))
+                .filter(|next| seen.insert(*next))
+                .collect();
+            let mut next_untried = untried.clone();
+            next_untried.extend(added.iter());
+            grow(order, polyomino, next_untried, seen, polyominoes);
+            for next in added.iter() {
+                seen.remove(next);
+            }
+        }
+        polyomino.pop();
+    }
+}
+
+// Polyominoes that cannot be rotated into each other, in canonical form
+pub fn one_sided_polyominoes(order: usize) -> Vec<Vec<GridPos>> {
+    distinct_by(fixed_polyominoes(order), canonical_one_sided)
+}
+
+// Polyominoes that cannot be rotated nor mirrored into each other, in canonical form
+pub fn free_polyominoes(order: usize) -> Vec<Vec<GridPos>> {
+    distinct_by(fixed_polyominoes(order), canonical)
+}
+
+fn distinct_by<F>(polyominoes: Vec<Vec<GridPos>>, canonical: F) -> Vec<Vec<GridPos>>
+where
+    F: Fn(&[GridPos]) -> Vec<GridPos>,
+{
+    let mut distinct: Vec<Vec<GridPos>> = polyominoes
+        .iter()
+        .map(|polyomino| canonical(polyomino))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    distinct.sort();
+    distinct
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piece::grid::parse_cells;
+
+    fn cells(shape: &str) -> Vec<GridPos> {
+        parse_cells(shape.lines()).unwrap()
+    }
+
+    fn counts(polyominoes: fn(usize) -> Vec<Vec<GridPos>>) -> Vec<usize> {
+        (1..=6).map(|order| polyominoes(order).len()).collect()
+    }
+
+    #[test]
+    fn polyomino_counts() {
+        assert_eq!(counts(fixed_polyominoes), vec![1, 2, 6, 19, 63, 216]);
+        assert_eq!(counts(one_sided_polyominoes), vec![1, 1, 2, 7, 18, 60]);
+        assert_eq!(counts(free_polyominoes), vec![1, 1, 2, 5, 12, 35]);
+        assert!(fixed_polyominoes(0).is_empty());
+    }
+
+    #[test]
+    fn fixed_polyominoes_are_distinct_and_normalized() {
+        let fixed = fixed_polyominoes(5);
+        let distinct: HashSet<&Vec<GridPos>> = fixed.iter().collect();
+        assert_eq!(distinct.len(), fixed.len());
+        assert!(fixed
+            .iter()
+            .all(|polyomino| normalize(polyomino) == *polyomino));
+    }
+
+    #[test]
+    fn symmetry_of_known_pieces() {
+        // Tetrominoes T, S and I, pentominoes V, X and F
+        assert_eq!(symmetry(&cells("XXX\n.X.")), Symmetry::AxisMirror);
+        assert_eq!(symmetry(&cells(".XX\nXX.")), Symmetry::HalfTurn);
+        assert_eq!(symmetry(&cells("X..\nX..\nXXX")), Symmetry::DiagonalMirror);
+        assert_eq!(symmetry(&cells("XXXX")), Symmetry::AxisMirrors);
+        assert_eq!(symmetry(&cells(".X.\nXXX\n.X.")), Symmetry::Full);
+        assert_eq!(symmetry(&cells(".XX\nXX.\n.X.")), Symmetry::None);
+    }
+
+    #[test]
+    fn distinct_orientations_follow_the_symmetry() {
+        assert_eq!(distinct_orientations(&cells("XXX\n.X.")), 4);
+        assert_eq!(distinct_orientations(&cells("XXXX")), 2);
+        assert_eq!(distinct_orientations(&cells(".X.\nXXX\n.X.")), 1);
+        assert_eq!(distinct_orientations(&cells(".XX\nXX.\n.X.")), 8);
+    }
+}
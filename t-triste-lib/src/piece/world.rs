@@ -0,0 +1,41 @@
+use bevy::prelude::*;
+
+use crate::piece::{grid::GridPos, SQUARE_WIDTH};
+
+// Pixels of the grid cells, only the rendering and the cursor need them
+pub trait WorldPos {
+    // The cell whose square contains the given window position.
+    fn at_world(pos: Vec2) -> Self;
+
+    // Center of the cell in window coordinates.
+    fn to_world(self, z_index: f32) -> Vec3;
+
+    fn contains_world(&self, pos: Vec2) -> bool;
+}
+
+impl WorldPos for GridPos {
+    fn at_world(pos: Vec2) -> Self {
+        let width = SQUARE_WIDTH as f32;
+        GridPos {
+            col: (pos.x / width).round() as i32,
+            row: (pos.y / width).round() as i32,
+        }
+    }
+
+    fn to_world(self, z_index: f32) -> Vec3 {
+        Vec3::new(
+            (self.col * SQUARE_WIDTH) as f32,
+            (self.row * SQUARE_WIDTH) as f32,
+            z_index,
+        )
+    }
+
+    fn contains_world(&self, pos: Vec2) -> bool {
+        let center = self.to_world(0.);
+        let half_width = (SQUARE_WIDTH / 2) as f32;
+        center.x - half_width <= pos.x
+            && pos.x <= center.x + half_width
+            && center.y - half_width <= pos.y
+            && pos.y <= center.y + half_width
+    }
+}
@@ -0,0 +1,366 @@
+use std::{
+    collections::BTreeMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    level::{CurrentLevel, LevelPack},
+    puzzle::PuzzleSolved,
+    state::AppState,
+    storage::data_dir,
+};
+
+const PROFILE_FILE: &str = "profile.ron";
+
+// Format of the profile written by this version of the game, each migration
+// adds a version
+pub const PROFILE_VERSION: u32 = MIGRATIONS.len() as u32 + 1;
+
+// Upgrades of the profile, one per format change: `MIGRATIONS[0]` turns a
+// version 1 profile into a version 2 one and so on. They work on the parsed
+// file, as the structs of older formats are gone.
+const MIGRATIONS: &[Migration] = &[];
+
+type Migration = fn(ron::Value) -> Result<ron::Value, ProfileError>;
+
+// Plugins
+// Keeps the progression of the player in `profile.ron` in the data directory
+pub struct ProfilePlugin;
+
+impl Plugin for ProfilePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(Profile::load_or_default())
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(record_solved.system().after("check_solved")),
+            )
+            .add_system_to_stage(CoreStage::Last, save_profile.system());
+    }
+}
+
+#[derive(Debug)]
+pub enum ProfileError {
+    Io(io::Error),
+    Parse(ron::Error),
+    // Written by a newer game, it is left alone
+    UnknownVersion(u32),
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileError::Io(error) => write!(f, "cannot access the profile: {}", error),
+            ProfileError::Parse(error) => write!(f, "invalid profile: {}", error),
+            ProfileError::UnknownVersion(version) => {
+                write!(f, "unknown profile version {}", version)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {}
+
+impl From<io::Error> for ProfileError {
+    fn from(error: io::Error) -> Self {
+        ProfileError::Io(error)
+    }
+}
+
+impl From<ron::Error> for ProfileError {
+    fn from(error: ron::Error) -> Self {
+        ProfileError::Parse(error)
+    }
+}
+
+// The best results of a level, each one can come from a different game
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LevelRecord {
+    pub completions: u32,
+    // In seconds
+    pub best_time: f64,
+    pub best_moves: u32,
+    pub best_hints: u32,
+    pub best_stars: u8,
+}
+
+impl LevelRecord {
+    fn new(solved: &PuzzleSolved) -> Self {
+        LevelRecord {
+            completions: 1,
+            best_time: solved.score.elapsed.as_secs_f64(),
+            best_moves: solved.score.moves,
+            best_hints: solved.score.hints,
+            best_stars: solved.stars,
+        }
+    }
+
+    fn add(&mut self, solved: &PuzzleSolved) {
+        self.completions += 1;
+        self.best_time = self.best_time.min(solved.score.elapsed.as_secs_f64());
+        self.best_moves = self.best_moves.min(solved.score.moves);
+        self.best_hints = self.best_hints.min(solved.score.hints);
+        self.best_stars = self.best_stars.max(solved.stars);
+    }
+}
+
+// Completed levels of a pack, by name
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PackProgress {
+    #[serde(default)]
+    pub levels: BTreeMap<String, LevelRecord>,
+}
+
+// Only the version is read first, to know which migrations the file needs
+#[derive(Deserialize)]
+struct ProfileVersion {
+    version: u32,
+}
+
+// Resources
+// What the player did, packs and levels are known by name so the progression
+// survives new levels.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub version: u32,
+    #[serde(default)]
+    pub packs: BTreeMap<String, PackProgress>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Profile {
+            version: PROFILE_VERSION,
+            packs: BTreeMap::new(),
+        }
+    }
+}
+
+impl Profile {
+    // The file in the data directory
+    pub fn path() -> Option<PathBuf> {
+        data_dir().map(|dir| dir.join(PROFILE_FILE))
+    }
+
+    // The saved profile, or a new one. An unreadable file is kept aside
+    // rather than overwritten.
+    pub fn load_or_default() -> Self {
+        let path = match Profile::path() {
+            Some(path) => path,
+            None => return Profile::default(),
+        };
+        if !path.exists() {
+            return Profile::default();
+        }
+        Profile::load(&path).unwrap_or_else(|error| {
+            let backup = path.with_extension("ron.bak");
+            warn!(
+                "{}: {}, it is moved to {}",
+                path.display(),
+                error,
+                backup.display()
+            );
+            if let Err(error) = fs::rename(&path, &backup) {
+                warn!("{}: {}", backup.display(), error);
+            }
+            Profile::default()
+        })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ProfileError> {
+        Profile::from_ron(&fs::read_to_string(path)?)
+    }
+
+    // Profiles of older versions are migrated to the current one
+    pub fn from_ron(content: &str) -> Result<Self, ProfileError> {
+        Profile::from_ron_with(content, MIGRATIONS)
+    }
+
+    // The current version is the one reached once every migration is applied
+    fn from_ron_with(content: &str, migrations: &[Migration]) -> Result<Self, ProfileError> {
+        let current = migrations.len() as u32 + 1;
+        let ProfileVersion { version } = ron::de::from_str(content)?;
+        if version == 0 || version > current {
+            return Err(ProfileError::UnknownVersion(version));
+        }
+        if version == current {
+            return Ok(ron::de::from_str(content)?);
+        }
+        let mut value: ron::Value = ron::de::from_str(content)?;
+        for migration in &migrations[version as usize - 1..] {
+            value = migration(value)?;
+        }
+        let mut profile: Profile = value.into_rust()?;
+        profile.version = current;
+        Ok(profile)
+    }
+
+    pub fn to_ron(&self) -> Result<String, ProfileError> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            // One line per level
+            ron::ser::PrettyConfig::new().with_depth_limit(4),
+        )?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ProfileError> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_ron()?)?;
+        Ok(())
+    }
+
+    pub fn record(&self, pack: &str, level: &str) -> Option<&LevelRecord> {
+        self.packs
+            .get(pack)
+            .and_then(|progress| progress.levels.get(level))
+    }
+
+    pub fn is_completed(&self, pack: &str, level: &str) -> bool {
+        self.record(pack, level).is_some()
+    }
+
+    // The first level is always open, the others once the one before is completed
+    pub fn is_unlocked(&self, pack: &LevelPack, index: usize) -> bool {
+        let completed = |index: usize| {
+            matches!(
+                pack.levels.get(index),
+                Some(level) if self.is_completed(&pack.name, &level.name)
+            )
+        };
+        index == 0 || completed(index - 1) || completed(index)
+    }
+
+    pub fn add_completion(&mut self, pack: &str, level: &str, solved: &PuzzleSolved) {
+        let levels = &mut self.packs.entry(pack.to_string()).or_default().levels;
+        match levels.get_mut(level) {
+            Some(record) => record.add(solved),
+            None => {
+                levels.insert(level.to_string(), LevelRecord::new(solved));
+            }
+        }
+    }
+}
+
+// Systems
+fn record_solved(
+    mut solved_events: EventReader<PuzzleSolved>,
+    pack: Res<LevelPack>,
+    current: Res<CurrentLevel>,
+    mut profile: ResMut<Profile>,
+) {
+    for solved in solved_events.iter() {
+        let level = &pack.levels[current.0];
+        profile.add_completion(&pack.name, &level.name, solved);
+    }
+}
+
+fn save_profile(profile: Res<Profile>) {
+    if !profile.is_changed() || profile.is_added() {
+        return;
+    }
+    if let Some(path) = Profile::path() {
+        if let Err(error) = profile.save(&path) {
+            warn!("{}: {}", path.display(), error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::score::Score;
+
+    fn solved(seconds: f64, moves: u32) -> PuzzleSolved {
+        PuzzleSolved {
+            score: Score {
+                elapsed: Duration::from_secs_f64(seconds),
+                moves,
+                hints: 0,
+            },
+            stars: 2,
+        }
+    }
+
+    // An imaginary first format, where the packs were called `progress`
+    fn rename_progress(value: ron::Value) -> Result<ron::Value, ProfileError> {
+        let mut fields = match value {
+            ron::Value::Map(fields) => fields,
+            value => return Ok(value),
+        };
+        let key = ron::Value::String("progress".to_string());
+        if let Some(packs) = fields.remove(&key) {
+            fields.insert(ron::Value::String("packs".to_string()), packs);
+        }
+        Ok(ron::Value::Map(fields))
+    }
+
+    #[test]
+    fn profile_round_trip() {
+        let mut profile = Profile::default();
+        profile.add_completion("T-Triste", "First steps", &solved(12.5, 9));
+        profile.add_completion("T-Triste", "First steps", &solved(20., 5));
+        let record = profile.record("T-Triste", "First steps").unwrap();
+        assert_eq!(record.completions, 2);
+        assert_eq!(record.best_time, 12.5);
+        assert_eq!(record.best_moves, 5);
+        assert_eq!(record.best_stars, 2);
+        let ron = profile.to_ron().unwrap();
+        assert_eq!(Profile::from_ron(&ron).unwrap(), profile);
+        // Levels are optional
+        assert_eq!(
+            Profile::from_ron("(version: 1)").unwrap(),
+            Profile::default()
+        );
+    }
+
+    #[test]
+    fn older_profiles_are_migrated() {
+        let old = r#"(
+            version: 1,
+            progress: {
+                "T-Triste": (levels: {
+                    "First steps": (
+                        completions: 3,
+                        best_time: 4.5,
+                        best_moves: 6,
+                        best_hints: 1,
+                        best_stars: 2,
+                    ),
+                }),
+            },
+        )"#;
+        let profile = Profile::from_ron_with(old, &[rename_progress]).unwrap();
+        assert_eq!(profile.version, 2);
+        let record = profile.record("T-Triste", "First steps").unwrap();
+        assert_eq!(record.completions, 3);
+        assert_eq!(record.best_time, 4.5);
+        assert_eq!(record.best_moves, 6);
+        // A profile already at the last version is read as is
+        let current = Profile::from_ron_with("(version: 2, packs: {})", &[rename_progress]);
+        assert_eq!(current.unwrap().packs, BTreeMap::new());
+    }
+
+    #[test]
+    fn unknown_versions_are_refused() {
+        let newer = format!("(version: {}, packs: {{}})", PROFILE_VERSION + 1);
+        assert!(matches!(
+            Profile::from_ron(&newer),
+            Err(ProfileError::UnknownVersion(version)) if version == PROFILE_VERSION + 1
+        ));
+        assert!(matches!(
+            Profile::from_ron("(version: 0)"),
+            Err(ProfileError::UnknownVersion(0))
+        ));
+        assert!(matches!(
+            Profile::from_ron("(packs: {})"),
+            Err(ProfileError::Parse(_))
+        ));
+    }
+}
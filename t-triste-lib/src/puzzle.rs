@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+
+use crate::{
+    level::Level,
+    piece::board::Board,
+    score::Score,
+    state::{AppState, GameMode},
+};
+
+// Plugins
+pub struct PuzzlePlugin;
+
+impl Plugin for PuzzlePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(PuzzleProgress::default())
+            .add_event::<PuzzleSolved>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing).with_system(start_puzzle.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing).with_system(
+                    check_solved
+                        .system()
+                        .label("check_solved")
+                        .after("incrust_in_board"),
+                ),
+            )
+            .add_system(log_solved.system());
+    }
+}
+
+// Events
+// Sent once every cell of the board is covered by a piece
+#[derive(Debug, Clone)]
+pub struct PuzzleSolved {
+    pub score: Score,
+    // Earned against the par of the level
+    pub stars: u8,
+}
+
+// Resources
+#[derive(Default)]
+pub struct PuzzleProgress {
+    pub solved: bool,
+}
+
+// Systems
+fn start_puzzle(mut progress: ResMut<PuzzleProgress>) {
+    *progress = PuzzleProgress::default();
+}
+
+fn check_solved(
+    level: Res<Level>,
+    mode: Res<GameMode>,
+    score: Res<Score>,
+    board: Res<Board>,
+    mut progress: ResMut<PuzzleProgress>,
+    mut solved_events: EventWriter<PuzzleSolved>,
+) {
+    // A race is won by filling a board first, see `versus`
+    if *mode == GameMode::Versus {
+        return;
+    }
+    if !board.is_complete() {
+        // Removing a piece means the puzzle has to be solved again
+        progress.solved = false;
+        return;
+    }
+    if progress.solved {
+        return;
+    }
+
+    progress.solved = true;
+    solved_events.send(PuzzleSolved {
+        score: *score,
+        stars: score.stars(&level.par()),
+    });
+}
+
+fn log_solved(mut solved_events: EventReader<PuzzleSolved>) {
+    for event in solved_events.iter() {
+        info!(
+            "Puzzle solved in {:.1}s with {} moves and {} hints, {} stars",
+            event.score.elapsed.as_secs_f32(),
+            event.score.moves,
+            event.score.hints,
+            event.stars
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use crate::{
+    level::{Difficulty, Level},
+    piece::{grid::GridPos, polyomino::distinct_orientations},
+    solver::Solver,
+};
+
+// Searches are cut there, a bigger puzzle is hard anyway
+const MAX_SOLUTIONS: usize = 100;
+const MAX_SEARCH_NODES: usize = 1_000_000;
+
+// Scores from which a puzzle gets harder
+const MEDIUM_SCORE: f32 = 8.;
+const HARD_SCORE: f32 = 14.;
+
+// What makes a puzzle hard or easy
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rating {
+    // Ways to solve it, turning the board or swapping identical pieces aside
+    pub solutions: usize,
+    // Nodes walked by the solver to find every solution
+    pub search_nodes: usize,
+    pub pieces: usize,
+    // Pieces that look the same once turned, so there are fewer ways to put them
+    pub symmetric_pieces: usize,
+    // Placements that can be made without any choice from the empty board
+    pub forced_moves: usize,
+    // The higher the harder, about 0 for a single piece
+    pub score: f32,
+}
+
+impl Rating {
+    pub fn difficulty(&self) -> Difficulty {
+        if self.score < MEDIUM_SCORE {
+            Difficulty::Easy
+        } else if self.score < HARD_SCORE {
+            Difficulty::Medium
+        } else {
+            Difficulty::Hard
+        }
+    }
+}
+
+// Rates a board against the pieces that should cover it
+pub fn rate(board: &[GridPos], pieces: &[Vec<GridPos>]) -> Rating {
+    let solver = Solver::from_cells(board, pieces);
+    let solutions = solver.count_tilings_up_to(MAX_SOLUTIONS);
+    let search_nodes = solver.search_tree_size_up_to(MAX_SEARCH_NODES);
+    let forced_moves = solver.forced_moves();
+    let symmetric_pieces = pieces
+        .iter()
+        .filter(|piece| distinct_orientations(piece) < 8)
+        .count();
+
+    // Trying placements is what takes time, more solutions and fewer choices make it faster
+    let nb_pieces = pieces.len() as f32;
+    let score = (search_nodes as f32).log2() + nb_pieces
+        - (solutions.max(1) as f32).log2()
+        - 0.5 * symmetric_pieces as f32
+        - 0.5 * forced_moves as f32;
+
+    Rating {
+        solutions,
+        search_nodes,
+        pieces: pieces.len(),
+        symmetric_pieces,
+        forced_moves,
+        score: score.max(0.),
+    }
+}
+
+pub fn rate_level(level: &Level) -> Rating {
+    let pieces: Vec<Vec<GridPos>> = level
+        .pieces()
+        .iter()
+        .map(|bundle| bundle.positions())
+        .collect();
+    rate(&level.board().positions(), &pieces)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn first_steps() -> Level {
+        Level::load(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/levels/01_first_steps.ron"
+        ))
+        .unwrap()
+    }
+
+    fn square(width: i32) -> Vec<GridPos> {
+        (0..width)
+            .flat_map(|col| (0..width).map(move |row| GridPos::new(col, row)))
+            .collect()
+    }
+
+    #[test]
+    fn rate_a_single_piece() {
+        let rating = rate(&square(1), &[vec![GridPos::new(3, 3)]]);
+        assert_eq!(rating.solutions, 1);
+        assert_eq!(rating.pieces, 1);
+        assert_eq!(rating.forced_moves, 1);
+        assert_eq!(rating.symmetric_pieces, 1);
+        assert_eq!(rating.difficulty(), Difficulty::Easy);
+    }
+
+    #[test]
+    fn rate_the_first_level() {
+        let rating = rate_level(&first_steps());
+        assert_eq!(rating.solutions, 33);
+        assert_eq!(rating.pieces, 5);
+        assert_eq!(rating.forced_moves, 0);
+        // Only the L tetromino has 8 orientations
+        assert_eq!(rating.symmetric_pieces, 4);
+    }
+
+    #[test]
+    fn harder_puzzles_score_higher() {
+        let domino = vec![GridPos::new(0, 0), GridPos::new(1, 0)];
+        let single = rate(&square(1), &[vec![GridPos::default()]]);
+        let dominoes = rate(&square(2), &[domino.clone(), domino]);
+        let first_steps = rate_level(&first_steps());
+        assert!(single.score < dominoes.score);
+        assert!(dominoes.score < first_steps.score);
+    }
+}
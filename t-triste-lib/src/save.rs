@@ -0,0 +1,392 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::{app::AppExit, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    history::{set_state, History, PieceState},
+    level::{CurrentLevel, LevelPack},
+    piece::{board::Board, GridTransform, PieceId, PieceShape},
+    puzzle::PuzzleProgress,
+    score::Score,
+    state::{AppState, GameMode},
+    storage::data_dir,
+};
+
+const SAVE_FILE: &str = "save.ron";
+
+// Plugins
+// Keeps the level being played in `save.ron` in the data directory, it is
+// written when leaving the level or the game and resumed on the next launch.
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(Resume(SavedGame::load_or_none()))
+            .add_startup_system(resume_level.system())
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing).with_system(restore_game.system()),
+            )
+            // Paused and LevelComplete are pushed over Playing
+            .add_system_set(SystemSet::on_pause(AppState::Playing).with_system(save_game.system()))
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(save_game.system()))
+            .add_system_to_stage(CoreStage::Last, save_on_exit.system());
+    }
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Parse(ron::Error),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(error) => write!(f, "cannot access the saved game: {}", error),
+            SaveError::Parse(error) => write!(f, "invalid saved game: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<io::Error> for SaveError {
+    fn from(error: io::Error) -> Self {
+        SaveError::Io(error)
+    }
+}
+
+impl From<ron::Error> for SaveError {
+    fn from(error: ron::Error) -> Self {
+        SaveError::Parse(error)
+    }
+}
+
+// A piece of a level in progress
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedPiece {
+    pub piece: PieceId,
+    pub state: PieceState,
+}
+
+// A level in progress, with what it takes to undo the moves already done
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedGame {
+    // Index of the level in the pack
+    pub level: usize,
+    // Tells if the pack changed since the game was saved
+    pub level_name: String,
+    pub pieces: Vec<SavedPiece>,
+    pub history: History,
+    #[serde(default)]
+    pub score: Score,
+}
+
+impl SavedGame {
+    // The file in the data directory
+    pub fn path() -> Option<PathBuf> {
+        data_dir().map(|dir| dir.join(SAVE_FILE))
+    }
+
+    // The game to resume, if there is a readable one
+    pub fn load_or_none() -> Option<Self> {
+        let path = SavedGame::path()?;
+        if !path.exists() {
+            return None;
+        }
+        SavedGame::load(&path)
+            .map_err(|error| warn!("{}: {}", path.display(), error))
+            .ok()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SaveError> {
+        SavedGame::from_ron(&fs::read_to_string(path)?)
+    }
+
+    pub fn from_ron(content: &str) -> Result<Self, SaveError> {
+        Ok(ron::de::from_str(content)?)
+    }
+
+    pub fn to_ron(&self) -> Result<String, SaveError> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            // One line per history step
+            ron::ser::PrettyConfig::new().with_depth_limit(3),
+        )?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SaveError> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_ron()?)?;
+        Ok(())
+    }
+
+    // Forgets the saved game, once the level is solved
+    pub fn remove<P: AsRef<Path>>(path: P) -> Result<(), SaveError> {
+        if path.as_ref().exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    // The saved level is still the same in the pack
+    pub fn matches(&self, pack: &LevelPack) -> bool {
+        matches!(pack.levels.get(self.level), Some(level) if level.name == self.level_name)
+    }
+}
+
+// Resources
+// The game read at startup, until it is put back on the board
+pub struct Resume(pub Option<SavedGame>);
+
+// Systems
+fn resume_level(
+    mut resume: ResMut<Resume>,
+    pack: Res<LevelPack>,
+    mut current: ResMut<CurrentLevel>,
+    mut state: ResMut<State<AppState>>,
+) {
+    let level = match &resume.0 {
+        Some(saved) if saved.matches(&pack) => saved.level,
+        Some(saved) => {
+            warn!(
+                "The saved level {} is not in the game anymore",
+                saved.level_name
+            );
+            resume.0 = None;
+            return;
+        }
+        None => return,
+    };
+    current.0 = level;
+    if let Err(error) = state.set(AppState::Playing) {
+        warn!("Cannot resume the saved game: {:?}", error);
+    }
+}
+
+// Pieces are spawned when entering Playing, they are moved once they exist
+fn restore_game(
+    mut commands: Commands,
+    mut resume: ResMut<Resume>,
+    current: Res<CurrentLevel>,
+    mut board: ResMut<Board>,
+    mut history: ResMut<History>,
+    mut score: ResMut<Score>,
+    mut pieces: Query<(Entity, &PieceId, &PieceShape, &mut GridTransform)>,
+) {
+    if resume.0.is_none() || pieces.iter_mut().next().is_none() {
+        return;
+    }
+    let saved = resume.0.take().unwrap();
+    if saved.level != current.0 {
+        return;
+    }
+    for (entity, id, shape, mut grid_transform) in pieces.iter_mut() {
+        if let Some(piece) = saved.pieces.iter().find(|piece| piece.piece == *id) {
+            set_state(
+                &mut commands,
+                &mut board,
+                entity,
+                *id,
+                shape,
+                &mut grid_transform,
+                piece.state,
+            );
+        }
+    }
+    *history = saved.history;
+    *score = saved.score;
+}
+
+#[allow(clippy::too_many_arguments)]
+fn save_game(
+    mode: Res<GameMode>,
+    pack: Res<LevelPack>,
+    current: Res<CurrentLevel>,
+    board: Res<Board>,
+    history: Res<History>,
+    score: Res<Score>,
+    progress: Res<PuzzleProgress>,
+    pieces: Query<(&PieceId, &GridTransform)>,
+) {
+    // A race is not resumed
+    if *mode == GameMode::Versus {
+        return;
+    }
+    write_save(
+        &pack, &current, &board, &history, &score, &progress, &pieces,
+    );
+}
+
+// The window is closing, the level is saved if one is being played
+#[allow(clippy::too_many_arguments)]
+fn save_on_exit(
+    mut exit_events: EventReader<AppExit>,
+    mode: Res<GameMode>,
+    pack: Res<LevelPack>,
+    current: Res<CurrentLevel>,
+    board: Res<Board>,
+    history: Res<History>,
+    score: Res<Score>,
+    progress: Res<PuzzleProgress>,
+    pieces: Query<(&PieceId, &GridTransform)>,
+) {
+    if exit_events.iter().next().is_some() && *mode == GameMode::Solo {
+        write_save(
+            &pack, &current, &board, &history, &score, &progress, &pieces,
+        );
+    }
+}
+
+fn write_save(
+    pack: &LevelPack,
+    current: &CurrentLevel,
+    board: &Board,
+    history: &History,
+    score: &Score,
+    progress: &PuzzleProgress,
+    pieces: &Query<(&PieceId, &GridTransform)>,
+) {
+    let path = match SavedGame::path() {
+        Some(path) => path,
+        None => return,
+    };
+    // Nothing to resume from the menus, the last saved game is kept
+    if pieces.iter().next().is_none() {
+        return;
+    }
+    let result = if progress.solved {
+        SavedGame::remove(&path)
+    } else {
+        let mut saved_pieces: Vec<SavedPiece> = pieces
+            .iter()
+            .map(|(id, grid_transform)| SavedPiece {
+                piece: *id,
+                state: PieceState::new(*grid_transform, board.is_placed(*id)),
+            })
+            .collect();
+        saved_pieces.sort_by_key(|piece| piece.piece.0);
+        SavedGame {
+            level: current.0,
+            level_name: pack.levels[current.0].name.clone(),
+            pieces: saved_pieces,
+            history: history.clone(),
+            score: *score,
+        }
+        .save(&path)
+    };
+    if let Err(error) = result {
+        warn!("{}: {}", path.display(), error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        history::{Action, ActionKind},
+        level::{Level, BUILTIN_LEVELS},
+        piece::{grid::GridPos, orientation::Orientation},
+    };
+
+    fn saved_game() -> SavedGame {
+        let start = PieceState::new(GridTransform::new(GridPos::new(2, 2)), false);
+        let mut moved = GridTransform::new(GridPos::new(7, 5));
+        moved.orientation = Orientation::all()[5];
+        let placed = PieceState::new(moved, true);
+        let mut history = History::default();
+        history.record(Action {
+            kind: ActionKind::Place,
+            piece: PieceId(1),
+            before: start,
+            after: placed,
+        });
+        SavedGame {
+            level: 0,
+            level_name: "First steps".to_string(),
+            pieces: vec![
+                SavedPiece {
+                    piece: PieceId(0),
+                    state: start,
+                },
+                SavedPiece {
+                    piece: PieceId(1),
+                    state: placed,
+                },
+            ],
+            history,
+            score: Score {
+                elapsed: Duration::from_millis(12_345),
+                moves: 3,
+                hints: 1,
+            },
+        }
+    }
+
+    fn pack() -> LevelPack {
+        LevelPack {
+            name: "T-Triste".to_string(),
+            levels: vec![Level::from_ron(BUILTIN_LEVELS[0]).unwrap()],
+        }
+    }
+
+    #[test]
+    fn saved_game_round_trip() {
+        let saved = saved_game();
+        assert_eq!(
+            SavedGame::from_ron(&saved.to_ron().unwrap()).unwrap(),
+            saved
+        );
+
+        let path = std::env::temp_dir()
+            .join(format!("t-triste-save-{}", std::process::id()))
+            .join(SAVE_FILE);
+        saved.save(&path).unwrap();
+        let loaded = SavedGame::load(&path);
+        SavedGame::remove(&path).unwrap();
+        assert!(!path.exists());
+        fs::remove_dir(path.parent().unwrap()).unwrap();
+        assert_eq!(loaded.unwrap(), saved);
+    }
+
+    #[test]
+    fn older_saves_have_no_score() {
+        let mut saved = saved_game();
+        let content = saved.to_ron().unwrap();
+        let start = content.find("score:").unwrap();
+        let without_score = format!("{})", &content[..start]);
+        saved.score = Score::default();
+        assert_eq!(SavedGame::from_ron(&without_score).unwrap(), saved);
+        assert!(matches!(
+            SavedGame::from_ron("(level: 0)"),
+            Err(SaveError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn only_the_same_level_is_resumed() {
+        let pack = pack();
+        let saved = saved_game();
+        assert!(saved.matches(&pack));
+        // The level was renamed or replaced
+        let other = SavedGame {
+            level_name: "Last steps".to_string(),
+            ..saved_game()
+        };
+        assert!(!other.matches(&pack));
+        // The level was removed from the pack
+        let missing = SavedGame {
+            level: 1,
+            ..saved_game()
+        };
+        assert!(!missing.matches(&pack));
+    }
+}
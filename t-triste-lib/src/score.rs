@@ -0,0 +1,186 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    level::{Level, Par},
+    menu::FONT,
+    state::{despawn_all, AppState, GameMode},
+};
+
+pub const MAX_STARS: u8 = 3;
+
+const HUD_SIZE: f32 = 20.;
+
+// Plugins
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(Score::default())
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing)
+                    .with_system(reset_score.system())
+                    .with_system(spawn_hud.system()),
+            )
+            // The timer stops while Paused or LevelComplete is pushed over Playing
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(tick_score.system().before("check_solved")),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Playing)
+                    .with_system(despawn_all::<ScoreText>.system()),
+            )
+            .add_system(draw_hud.system());
+    }
+}
+
+// Components
+// Marker of the HUD text
+struct ScoreText;
+
+// Resources
+// How the current level is being played
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Score {
+    // Time spent playing the level, pauses excluded
+    pub elapsed: Duration,
+    // Placements, rotations, flips and removals
+    pub moves: u32,
+    pub hints: u32,
+}
+
+impl Score {
+    // A star for solving the level, one for each par beaten. Each hint costs a
+    // star, but a solved level keeps at least one.
+    pub fn stars(&self, par: &Par) -> u8 {
+        let mut stars: u8 = 1;
+        if self.elapsed.as_secs_f64() <= par.time {
+            stars += 1;
+        }
+        if self.moves <= par.moves {
+            stars += 1;
+        }
+        let cost = self.hints.min(MAX_STARS as u32) as u8;
+        stars.saturating_sub(cost).max(1)
+    }
+}
+
+// Filled then empty stars, like ★★☆
+pub fn stars_text(stars: u8) -> String {
+    (0..MAX_STARS)
+        .map(|star| if star < stars { '★' } else { '☆' })
+        .collect()
+}
+
+// Systems
+fn reset_score(mut score: ResMut<Score>) {
+    *score = Score::default();
+}
+
+fn tick_score(time: Res<Time>, mut score: ResMut<Score>) {
+    score.elapsed += time.delta();
+}
+
+fn spawn_hud(mut commands: Commands, asset_server: Res<AssetServer>, mode: Res<GameMode>) {
+    // A race has no par to beat
+    if *mode == GameMode::Versus {
+        return;
+    }
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.),
+                    top: Val::Px(10.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load(FONT),
+                    font_size: HUD_SIZE,
+                    color: Color::rgb(0.35, 0.20, 0.),
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(ScoreText);
+}
+
+fn draw_hud(score: Res<Score>, level: Res<Level>, mut texts: Query<&mut Text, With<ScoreText>>) {
+    let par = level.par();
+    let value = format!(
+        "Temps {:.0}/{:.0} s   Coups {}/{}   Indices {}   {}",
+        score.elapsed.as_secs_f64().floor(),
+        par.time,
+        score.moves,
+        par.moves,
+        score.hints,
+        stars_text(score.stars(&par))
+    );
+    for mut text in texts.iter_mut() {
+        // Changing the text lays it out again, once a second is enough
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAR: Par = Par {
+        time: 60.,
+        moves: 8,
+    };
+
+    fn score(seconds: u64, moves: u32, hints: u32) -> Score {
+        Score {
+            elapsed: Duration::from_secs(seconds),
+            moves,
+            hints,
+        }
+    }
+
+    #[test]
+    fn each_par_beaten_earns_a_star() {
+        assert_eq!(score(90, 12, 0).stars(&PAR), 1);
+        assert_eq!(score(30, 12, 0).stars(&PAR), 2);
+        assert_eq!(score(90, 5, 0).stars(&PAR), 2);
+        assert_eq!(score(30, 5, 0).stars(&PAR), MAX_STARS);
+        // Matching the par is enough
+        assert_eq!(score(60, 8, 0).stars(&PAR), MAX_STARS);
+        assert_eq!(
+            Score {
+                elapsed: Duration::from_millis(60_001),
+                moves: 9,
+                hints: 0
+            }
+            .stars(&PAR),
+            1
+        );
+    }
+
+    #[test]
+    fn hints_cost_a_star_down_to_one() {
+        assert_eq!(score(30, 5, 1).stars(&PAR), 2);
+        assert_eq!(score(30, 5, 2).stars(&PAR), 1);
+        assert_eq!(score(30, 5, 10).stars(&PAR), 1);
+        assert_eq!(score(90, 12, 1).stars(&PAR), 1);
+    }
+
+    #[test]
+    fn stars_are_drawn_filled_first() {
+        assert_eq!(stars_text(0), "☆☆☆");
+        assert_eq!(stars_text(2), "★★☆");
+        assert_eq!(stars_text(MAX_STARS), "★★★");
+    }
+}
@@ -0,0 +1,503 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::piece::{
+    board::Board,
+    grid::GridPos,
+    orientation::Orientation,
+    polyomino::{bottom_left, symmetries},
+};
+
+// A piece put somewhere on the board
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placement {
+    // Index of the piece in the list given to the solver
+    pub piece: usize,
+    pub cells: Vec<GridPos>,
+}
+
+// One placement per piece, ordered by piece index
+pub type Solution = Vec<Placement>;
+
+// Finds the ways to tile a board with a set of pieces, each piece being used once.
+// Pieces can be rotated and mirrored. This is an exact cover problem solved with
+// Knuth's Algorithm X on dancing links.
+// Identical pieces or a symmetric board give solutions that look the same:
+// they are all counted.
+pub struct Solver {
+    nb_cells: usize,
+    nb_pieces: usize,
+    placements: Vec<Placement>,
+    // Column of each cell of the board
+    columns: HashMap<GridPos, usize>,
+}
+
+impl Solver {
+    pub fn new(board: &Board, pieces: &[Vec<GridPos>]) -> Self {
+        Solver::from_cells(&board.positions(), pieces)
+    }
+
+    pub fn from_cells(board: &[GridPos], pieces: &[Vec<GridPos>]) -> Self {
+        let board_cells: HashSet<GridPos> = board.iter().copied().collect();
+        let mut cells: Vec<GridPos> = board_cells.iter().copied().collect();
+        cells.sort();
+        // Pieces columns come first, then one column per board cell
+        let columns = cells
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| (*cell, pieces.len() + i))
+            .collect();
+
+        let mut placements = vec![];
+        for (piece, shape) in pieces.iter().enumerate() {
+            for orientation in orientations(shape) {
+                for anchor in cells.iter() {
+                    // Orientations are normalized, their first cell is at (0, 0)
+                    let moved: Vec<GridPos> =
+                        orientation.iter().map(|cell| *cell + *anchor).collect();
+                    if moved.iter().all(|cell| board_cells.contains(cell)) {
+                        placements.push(Placement {
+                            piece,
+                            cells: moved,
+                        });
+                    }
+                }
+            }
+        }
+
+        Solver {
+            nb_cells: cells.len(),
+            nb_pieces: pieces.len(),
+            placements,
+            columns,
+        }
+    }
+
+    // Only keeps the placement of the piece on these cells,
+    // to look for solutions that go through pieces already put on the board.
+    pub fn fix(&mut self, piece: usize, cells: &[GridPos]) {
+        let mut cells = cells.to_vec();
+        cells.sort();
+        // Placements cells are sorted as they come from normalized orientations
+        self.placements
+            .retain(|placement| placement.piece != piece || placement.cells == cells);
+    }
+
+    pub fn first_solution(&self) -> Option<Solution> {
+        let mut solution = None;
+        self.search(usize::MAX, |rows| {
+            solution = Some(self.solution(rows));
+            false
+        });
+        solution
+    }
+
+    pub fn count_solutions(&self) -> usize {
+        self.count_solutions_up_to(usize::MAX)
+    }
+
+    // Stops counting once `limit` solutions are found
+    pub fn count_solutions_up_to(&self, limit: usize) -> usize {
+        let mut count = 0;
+        if limit == 0 {
+            return count;
+        }
+        self.search(usize::MAX, |_| {
+            count += 1;
+            count < limit
+        });
+        count
+    }
+
+    pub fn has_unique_solution(&self) -> bool {
+        self.count_solutions_up_to(2) == 1
+    }
+
+    // Counts the ways the board is cut, stopping once `limit` are found: solutions that
+    // only differ by identical pieces being swapped or by turning the whole board are
+    // counted once.
+    pub fn count_tilings_up_to(&self, limit: usize) -> usize {
+        if limit == 0 {
+            return 0;
+        }
+
+        // Turning the board keeps its cells once moved back by an offset
+        let board: Vec<GridPos> = self.columns.keys().copied().collect();
+        let turns: Vec<(Orientation, GridPos)> = symmetries(&board)
+            .into_iter()
+            .map(|orientation| {
+                let turned: Vec<GridPos> =
+                    board.iter().map(|cell| orientation.apply(*cell)).collect();
+                (orientation, bottom_left(&board) - bottom_left(&turned))
+            })
+            .collect();
+
+        let mut tilings = HashSet::new();
+        self.search(usize::MAX, |rows| {
+            let tiling = turns
+                .iter()
+                .map(|(orientation, offset)| {
+                    let mut pieces: Vec<Vec<GridPos>> = rows
+                        .iter()
+                        .map(|row| {
+                            let mut cells: Vec<GridPos> = self.placements[*row]
+                                .cells
+                                .iter()
+                                .map(|cell| orientation.apply(*cell) + *offset)
+                                .collect();
+                            cells.sort();
+                            cells
+                        })
+                        .collect();
+                    pieces.sort();
+                    pieces
+                })
+                .min()
+                .unwrap();
+            tilings.insert(tiling);
+            tilings.len() < limit
+        });
+        tilings.len()
+    }
+
+    pub fn has_unique_tiling(&self) -> bool {
+        self.count_tilings_up_to(2) == 1
+    }
+
+    // Nodes of the search tree walked to find every solution, stopping at `limit`.
+    // It grows with the choices a player has to try.
+    pub fn search_tree_size_up_to(&self, limit: usize) -> usize {
+        self.search(limit, |_| true)
+    }
+
+    // Placements that are the only way to fill a cell or to use a piece, one after
+    // the other, before any choice has to be made
+    pub fn forced_moves(&self) -> usize {
+        self.links().forced_moves()
+    }
+
+    // Calls `visit` with the placements of each solution until it returns false,
+    // or until `max_nodes` nodes of the search tree were walked. Returns the nodes walked.
+    fn search<F: FnMut(&[usize]) -> bool>(&self, max_nodes: usize, mut visit: F) -> usize {
+        let mut links = self.links();
+        links.max_nodes = max_nodes;
+        links.search(&mut vec![], &mut visit);
+        links.nodes
+    }
+
+    fn links(&self) -> DancingLinks {
+        let mut links = DancingLinks::new(self.nb_pieces + self.nb_cells);
+        for placement in self.placements.iter() {
+            let mut row = vec![placement.piece];
+            row.extend(placement.cells.iter().map(|cell| self.columns[cell]));
+            links.add_row(&row);
+        }
+        links
+    }
+
+    fn solution(&self, rows: &[usize]) -> Solution {
+        let mut solution: Solution = rows
+            .iter()
+            .map(|row| self.placements[*row].clone())
+            .collect();
+        solution.sort_by_key(|placement| placement.piece);
+        solution
+    }
+}
+
+// The distinct rotations and reflections of a shape, moved so their first cell is (0, 0)
+pub fn orientations(shape: &[GridPos]) -> Vec<Vec<GridPos>> {
+    let mut orientations: Vec<Vec<GridPos>> = vec![];
+    for mirrored in [false, true].iter() {
+        let mut cells: Vec<GridPos> = shape
+            .iter()
+            .map(|cell| {
+                if *mirrored {
+                    GridPos::new(-cell.col, cell.row)
+                } else {
+                    *cell
+                }
+            })
+            .collect();
+        for _ in 0..4 {
+            cells = cells
+                .iter()
+                .map(|cell| cell.rotate_around(GridPos::default()))
+                .collect();
+            let normalized = normalize(&cells);
+            if !orientations.contains(&normalized) {
+                orientations.push(normalized);
+            }
+        }
+    }
+    orientations
+}
+
+// Sorts the cells and moves them so the first one is at (0, 0)
+fn normalize(cells: &[GridPos]) -> Vec<GridPos> {
+    let mut cells = cells.to_vec();
+    cells.sort();
+    if let Some(first) = cells.first().copied() {
+        for cell in cells.iter_mut() {
+            *cell = *cell - first;
+        }
+    }
+    cells
+}
+
+// Sparse matrix of Algorithm X. Node 0 is the root, nodes 1..=nb_columns are the
+// column headers, the other nodes are the 1s of the matrix.
+struct DancingLinks {
+    left: Vec<usize>,
+    right: Vec<usize>,
+    up: Vec<usize>,
+    down: Vec<usize>,
+    column: Vec<usize>,
+    row: Vec<usize>,
+    size: Vec<usize>,
+    nb_rows: usize,
+    // Search tree nodes walked so far, the search stops at `max_nodes`
+    nodes: usize,
+    max_nodes: usize,
+}
+
+impl DancingLinks {
+    fn new(nb_columns: usize) -> Self {
+        let nb_nodes = nb_columns + 1;
+        DancingLinks {
+            left: (0..nb_nodes)
+                .map(|i| (i + nb_nodes - 1) % nb_nodes)
+                .collect(),
+            right: (0..nb_nodes).map(|i| (i + 1) % nb_nodes).collect(),
+            up: (0..nb_nodes).collect(),
+            down: (0..nb_nodes).collect(),
+            column: (0..nb_nodes).collect(),
+            row: vec![usize::MAX; nb_nodes],
+            size: vec![0; nb_nodes],
+            nb_rows: 0,
+            nodes: 0,
+            max_nodes: usize::MAX,
+        }
+    }
+
+    // `columns` are 0 based
+    fn add_row(&mut self, columns: &[usize]) {
+        let mut first: Option<usize> = None;
+        for column in columns.iter().map(|column| column + 1) {
+            let node = self.left.len();
+            self.column.push(column);
+            self.row.push(self.nb_rows);
+            self.size[column] += 1;
+
+            // Insert at the bottom of the column
+            self.up.push(self.up[column]);
+            self.down.push(column);
+            let last = self.up[column];
+            self.down[last] = node;
+            self.up[column] = node;
+
+            // Insert at the end of the row
+            match first {
+                None => {
+                    self.left.push(node);
+                    self.right.push(node);
+                    first = Some(node);
+                }
+                Some(first) => {
+                    self.left.push(self.left[first]);
+                    self.right.push(first);
+                    let last = self.left[first];
+                    self.right[last] = node;
+                    self.left[first] = node;
+                }
+            }
+        }
+        self.nb_rows += 1;
+    }
+
+    fn cover(&mut self, column: usize) {
+        self.right[self.left[column]] = self.right[column];
+        self.left[self.right[column]] = self.left[column];
+        let mut i = self.down[column];
+        while i != column {
+            let mut j = self.right[i];
+            while j != i {
+                self.down[self.up[j]] = self.down[j];
+                self.up[self.down[j]] = self.up[j];
+                self.size[self.column[j]] -= 1;
+                j = self.right[j];
+            }
+            i = self.down[i];
+        }
+    }
+
+    fn uncover(&mut self, column: usize) {
+        let mut i = self.up[column];
+        while i != column {
+            let mut j = self.left[i];
+            while j != i {
+                self.size[self.column[j]] += 1;
+                self.down[self.up[j]] = j;
+                self.up[self.down[j]] = j;
+                j = self.left[j];
+            }
+            i = self.up[i];
+        }
+        self.right[self.left[column]] = column;
+        self.left[self.right[column]] = column;
+    }
+
+    // The column with the fewest rows keeps the search tree small
+    fn smallest_column(&self) -> usize {
+        let mut column = self.right[0];
+        let mut j = self.right[column];
+        while j != 0 {
+            if self.size[j] < self.size[column] {
+                column = j;
+            }
+            j = self.right[j];
+        }
+        column
+    }
+
+    // Covers the placements left with a single choice, the links are not restored
+    fn forced_moves(&mut self) -> usize {
+        let mut forced_moves = 0;
+        while self.right[0] != 0 {
+            let column = self.smallest_column();
+            if self.size[column] != 1 {
+                break;
+            }
+            let i = self.down[column];
+            self.cover(column);
+            let mut j = self.right[i];
+            while j != i {
+                self.cover(self.column[j]);
+                j = self.right[j];
+            }
+            forced_moves += 1;
+        }
+        forced_moves
+    }
+
+    // Returns false once `visit` asked to stop or the tree got too big
+    fn search<F: FnMut(&[usize]) -> bool>(&mut self, rows: &mut Vec<usize>, visit: &mut F) -> bool {
+        self.nodes += 1;
+        if self.nodes >= self.max_nodes {
+            return false;
+        }
+        if self.right[0] == 0 {
+            return visit(rows);
+        }
+
+        let column = self.smallest_column();
+        if self.size[column] == 0 {
+            return true;
+        }
+
+        self.cover(column);
+        let mut keep_going = true;
+        let mut i = self.down[column];
+        while i != column && keep_going {
+            rows.push(self.row[i]);
+            let mut j = self.right[i];
+            while j != i {
+                self.cover(self.column[j]);
+                j = self.right[j];
+            }
+
+            keep_going = self.search(rows, visit);
+
+            let mut j = self.left[i];
+            while j != i {
+                self.uncover(self.column[j]);
+                j = self.left[j];
+            }
+            rows.pop();
+            i = self.down[i];
+        }
+        self.uncover(column);
+        keep_going
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rectangle(width: i32, height: i32) -> Vec<GridPos> {
+        let mut cells = vec![];
+        for col in 0..width {
+            for row in 0..height {
+                cells.push(GridPos::new(col, row));
+            }
+        }
+        cells
+    }
+
+    fn domino() -> Vec<GridPos> {
+        rectangle(2, 1)
+    }
+
+    #[test]
+    fn first_solution_covers_the_board() {
+        let solver = Solver::from_cells(&rectangle(2, 2), &[domino(), domino()]);
+        let solution = solver.first_solution().unwrap();
+        assert_eq!(solution.len(), 2);
+        assert_eq!(solution[0].piece, 0);
+        assert_eq!(solution[1].piece, 1);
+        let mut cells: Vec<GridPos> = solution
+            .iter()
+            .flat_map(|placement| placement.cells.clone())
+            .collect();
+        cells.sort();
+        assert_eq!(cells, rectangle(2, 2));
+    }
+
+    #[test]
+    fn count_solutions_on_small_boards() {
+        // Both dominoes lying or standing, in either order
+        let square = Solver::from_cells(&rectangle(2, 2), &[domino(), domino()]);
+        assert_eq!(square.count_solutions(), 4);
+        assert_eq!(square.count_solutions_up_to(3), 3);
+        assert!(!square.has_unique_solution());
+        // 3 ways to cut a 3x2 rectangle in dominoes, times 3! orders
+        let dominoes = vec![domino(); 3];
+        let rectangle = Solver::from_cells(&rectangle(3, 2), &dominoes);
+        assert_eq!(rectangle.count_solutions(), 18);
+        // A single cell board has a single solution
+        let single = Solver::from_cells(&[GridPos::new(4, 2)], &[vec![GridPos::default()]]);
+        assert!(single.has_unique_solution());
+    }
+
+    #[test]
+    fn fix_keeps_the_solutions_through_a_placement() {
+        let mut solver = Solver::from_cells(&rectangle(2, 2), &[domino(), domino()]);
+        solver.fix(0, &[GridPos::new(1, 0), GridPos::new(0, 0)]);
+        assert_eq!(solver.count_solutions(), 1);
+    }
+
+    #[test]
+    fn has_unique_tiling_ignores_symmetries_and_identical_pieces() {
+        // Lying and standing dominoes are the same cut, turned
+        let square = Solver::from_cells(&rectangle(2, 2), &[domino(), domino()]);
+        assert_eq!(square.count_tilings_up_to(10), 1);
+        assert!(square.has_unique_tiling());
+        // Three standing dominoes, or one standing beside two lying ones
+        let dominoes = vec![domino(); 3];
+        let rectangle = Solver::from_cells(&rectangle(3, 2), &dominoes);
+        assert_eq!(rectangle.count_tilings_up_to(10), 2);
+        assert!(!rectangle.has_unique_tiling());
+    }
+
+    #[test]
+    fn unsolvable_pieces() {
+        // The straight tromino only fits lying, the L can't fill the row left
+        let line = rectangle(3, 1);
+        let l = vec![GridPos::new(0, 0), GridPos::new(1, 0), GridPos::new(0, 1)];
+        let solver = Solver::from_cells(&rectangle(3, 2), &[line, l]);
+        assert_eq!(solver.first_solution(), None);
+        assert_eq!(solver.count_solutions(), 0);
+        assert_eq!(solver.count_tilings_up_to(10), 0);
+        assert!(!solver.has_unique_tiling());
+    }
+}
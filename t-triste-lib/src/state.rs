@@ -0,0 +1,27 @@
+use bevy::prelude::*;
+
+// The screens of the game. Pieces are only moved while Playing, Paused and
+// LevelComplete are pushed over it so the board stays as it was.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AppState {
+    MainMenu,
+    LevelSelect,
+    Playing,
+    Paused,
+    LevelComplete,
+}
+
+// Chosen in the main menu, a race puts a second board for the keyboard player
+// beside the one of the mouse player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    Solo,
+    Versus,
+}
+
+// Despawns what a state spawned, with its children, when leaving it
+pub fn despawn_all<T: Component>(mut commands: Commands, query: Query<Entity, With<T>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
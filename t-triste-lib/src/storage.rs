@@ -0,0 +1,38 @@
+use std::{env, path::PathBuf};
+
+const APP_DIR: &str = "t-triste";
+
+// Where the player settings go, following the habits of each system:
+// %APPDATA%\t-triste on Windows, ~/Library/Application Support/t-triste on macOS
+// and $XDG_CONFIG_HOME/t-triste or ~/.config/t-triste elsewhere.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = if cfg!(target_os = "windows") {
+        env_dir("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME").map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env_dir("XDG_CONFIG_HOME").or_else(|| env_dir("HOME").map(|home| home.join(".config")))
+    };
+    base.map(|base| base.join(APP_DIR))
+}
+
+// Where the saved games go: %APPDATA%\t-triste on Windows, ~/Library/Application Support/t-triste
+// on macOS and $XDG_DATA_HOME/t-triste or ~/.local/share/t-triste elsewhere.
+pub fn data_dir() -> Option<PathBuf> {
+    let base = if cfg!(target_os = "windows") {
+        env_dir("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME").map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env_dir("XDG_DATA_HOME")
+            .or_else(|| env_dir("HOME").map(|home| home.join(".local").join("share")))
+    };
+    base.map(|base| base.join(APP_DIR))
+}
+
+// Unset or empty variables are ignored, as the XDG spec asks
+fn env_dir(name: &str) -> Option<PathBuf> {
+    env::var_os(name)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
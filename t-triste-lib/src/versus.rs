@@ -0,0 +1,247 @@
+use bevy::prelude::*;
+
+use crate::{
+    level::Level,
+    piece::{
+        board::{spawn_board_sprites, Board, BoardMaterial},
+        grid::GridPos,
+        spawn_piece, PieceBundle, PieceId, PieceMaterials, SQUARE_WIDTH,
+    },
+    state::{AppState, GameMode},
+    WINDOW_HEIGHT, WINDOW_WIDTH,
+};
+
+// Player two plays on a copy of the level, this far on the right
+const RIVAL_OFFSET: GridPos = GridPos::new(WINDOW_WIDTH as i32 / SQUARE_WIDTH, 0);
+
+// Plugins
+// A race between two players on the same level: player one plays with the
+// mouse on the left, player two with the keyboard or a gamepad on the right.
+pub struct VersusPlugin;
+
+impl Plugin for VersusPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(GameMode::Solo)
+            .insert_resource(RivalBoard(Board::new(GridPos::default(), vec![])))
+            .insert_resource(Winner(None))
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing)
+                    .with_system(spawn_rival.system().after("spawn_level")),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(check_race.system().after("incrust_in_board")),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Playing).with_system(restore_window.system()),
+            );
+    }
+}
+
+// Components
+// The side of a piece
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Player {
+    // With the mouse, and with the keyboard or a gamepad in solo
+    One,
+    // With the keyboard or a gamepad
+    Two,
+}
+
+impl Player {
+    // The player using the keyboard and the gamepad
+    pub fn with_keyboard(mode: GameMode) -> Player {
+        match mode {
+            GameMode::Solo => Player::One,
+            GameMode::Versus => Player::Two,
+        }
+    }
+}
+
+// Resources
+// The board of player two, player one uses the `Board` resource
+pub struct RivalBoard(pub Board);
+
+// The first player to fill their board
+pub struct Winner(pub Option<Player>);
+
+// The board the pieces of a player are placed on
+pub fn player_board<'a>(
+    player: Player,
+    board: &'a mut Board,
+    rival_board: &'a mut RivalBoard,
+) -> &'a mut Board {
+    match player {
+        Player::One => board,
+        Player::Two => &mut rival_board.0,
+    }
+}
+
+// The board of the level for player two, on the right
+pub fn rival_board_of(level: &Level) -> Board {
+    let mut board = level.board();
+    board.origin = board.origin + RIVAL_OFFSET;
+    board
+}
+
+// The pieces of player two come after the ones of player one, each piece
+// keeps its own id on the boards
+pub fn rival_pieces(level: &Level) -> Vec<PieceBundle> {
+    let nb_pieces = level.pieces.len();
+    level
+        .pieces()
+        .into_iter()
+        .map(|mut bundle| {
+            bundle.id = PieceId(nb_pieces + bundle.id.0);
+            bundle.player = Player::Two;
+            bundle.grid_transform.translate(RIVAL_OFFSET);
+            bundle
+        })
+        .collect()
+}
+
+// Systems
+#[allow(clippy::too_many_arguments)]
+fn spawn_rival(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut piece_materials: ResMut<PieceMaterials>,
+    board_material: Res<BoardMaterial>,
+    mut windows: ResMut<Windows>,
+    mode: Res<GameMode>,
+    level: Res<Level>,
+    mut rival_board: ResMut<RivalBoard>,
+    mut winner: ResMut<Winner>,
+) {
+    winner.0 = None;
+    if *mode != GameMode::Versus {
+        return;
+    }
+    // Each player gets the size of a solo window
+    if let Some(window) = windows.get_primary_mut() {
+        window.set_resolution(2. * WINDOW_WIDTH, WINDOW_HEIGHT);
+    }
+
+    let board = rival_board_of(&level);
+    spawn_board_sprites(&mut commands, &board_material, &board);
+    rival_board.0 = board;
+    for bundle in rival_pieces(&level) {
+        let material = piece_materials.get(&mut materials, bundle.color.0);
+        spawn_piece(&mut commands, material, bundle);
+    }
+}
+
+fn check_race(
+    mode: Res<GameMode>,
+    board: Res<Board>,
+    rival_board: Res<RivalBoard>,
+    mut winner: ResMut<Winner>,
+    mut state: ResMut<State<AppState>>,
+) {
+    if *mode != GameMode::Versus || winner.0.is_some() {
+        return;
+    }
+    winner.0 = if board.is_complete() {
+        Some(Player::One)
+    } else if rival_board.0.is_complete() {
+        Some(Player::Two)
+    } else {
+        return;
+    };
+    if let Err(error) = state.push(AppState::LevelComplete) {
+        warn!("Cannot end the race: {:?}", error);
+    }
+}
+
+fn restore_window(mut windows: ResMut<Windows>) {
+    if let Some(window) = windows.get_primary_mut() {
+        if window.width() != WINDOW_WIDTH {
+            window.set_resolution(WINDOW_WIDTH, WINDOW_HEIGHT);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piece::board::PlacementResult;
+
+    // Two squares lying on their board
+    fn level() -> Level {
+        Level::from_ron(
+            "(
+                name: \"Race\",
+                difficulty: Easy,
+                board: (origin: (col: 1, row: 1), shape: [\"XX\"]),
+                pieces: [
+                    (kind: Square, position: (col: 1, row: 1)),
+                    (kind: Square, position: (col: 2, row: 1)),
+                ],
+            )",
+        )
+        .unwrap()
+    }
+
+    fn place(board: &mut Board, bundle: &PieceBundle) {
+        assert!(matches!(
+            board.place(bundle.id, &bundle.positions()),
+            PlacementResult::Placed { .. }
+        ));
+    }
+
+    #[test]
+    fn each_player_plays_on_their_board() {
+        let level = level();
+        let mut board = level.board();
+        let mut rival_board = RivalBoard(rival_board_of(&level));
+        assert_eq!(rival_board.0.origin, board.origin + RIVAL_OFFSET);
+
+        for bundle in level.pieces() {
+            assert_eq!(bundle.player, Player::One);
+            place(
+                player_board(bundle.player, &mut board, &mut rival_board),
+                &bundle,
+            );
+        }
+        assert!(board.is_complete());
+        assert!(!rival_board.0.is_complete());
+
+        for bundle in rival_pieces(&level) {
+            assert_eq!(bundle.player, Player::Two);
+            place(
+                player_board(bundle.player, &mut board, &mut rival_board),
+                &bundle,
+            );
+        }
+        assert!(rival_board.0.is_complete());
+        assert_eq!(
+            rival_board.0.owner(&(GridPos::new(1, 1) + RIVAL_OFFSET)),
+            Some(PieceId(2))
+        );
+        assert_eq!(board.owner(&GridPos::new(1, 1)), Some(PieceId(0)));
+    }
+
+    #[test]
+    fn player_two_ids_follow_player_one() {
+        let level = level();
+        let ids = |pieces: Vec<PieceBundle>| -> Vec<PieceId> {
+            pieces.iter().map(|bundle| bundle.id).collect()
+        };
+        assert_eq!(ids(level.pieces()), vec![PieceId(0), PieceId(1)]);
+        assert_eq!(ids(rival_pieces(&level)), vec![PieceId(2), PieceId(3)]);
+
+        // On a same board the first piece of each player keeps its own cell
+        let mut board = level.board();
+        let first = &level.pieces()[0];
+        let mut rival_first = rival_pieces(&level).remove(0);
+        rival_first
+            .grid_transform
+            .translate(GridPos::new(1, 0) - RIVAL_OFFSET);
+        place(&mut board, first);
+        place(&mut board, &rival_first);
+        assert_eq!(board.owner(&GridPos::new(1, 1)), Some(PieceId(0)));
+        assert_eq!(board.owner(&GridPos::new(2, 1)), Some(PieceId(2)));
+        assert!(board.is_placed(PieceId(0)));
+        assert!(board.is_placed(PieceId(2)));
+    }
+}
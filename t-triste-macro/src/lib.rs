@@ -1,89 +1,242 @@
 extern crate proc_macro;
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, DeriveInput};
+use syn::{parse_macro_input, DeriveInput, Error, Lit, Meta, NestedMeta};
 
-#[proc_macro_derive(PieceBehavior)]
+// Implements `Piece` and a `new(start_x, start_y)` constructor from an attribute:
+//
+// #[derive(PieceBehavior)]
+// #[piece(shape = "X.\nX.\nXX", color = "#ff2008")]
+// pub struct L {
+//     anchor: GridPos,
+// }
+//
+// The shape is drawn top row first, `X` being a square and `.` a hole.
+#[proc_macro_derive(PieceBehavior, attributes(piece))]
 pub fn derive_behavior_fn(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(expanded) => TokenStream::from(expanded),
+        Err(error) => TokenStream::from(error.to_compile_error()),
+    }
+}
+
+fn expand(input: &DeriveInput) -> Result<proc_macro2::TokenStream, Error> {
     let name = &input.ident;
-    let expanded = quote!{
-        impl crate::piece::Piece for #name {
-            fn positions(&self) -> Vec<Vec3> {
-                self.positions.clone()
+    let (shape, color) = piece_attribute(input)?;
+    let rows = parse_shape(&shape.value()).map_err(|message| Error::new(shape.span(), message))?;
+    let (red, green, blue, alpha) =
+        parse_color(&color.value()).map_err(|message| Error::new(color.span(), message))?;
+
+    // Each horizontal run of squares is built as a rectangle, bottom row first
+    let mut rectangles = vec![];
+    for (row, cols) in rows.iter().enumerate() {
+        let mut col = 0;
+        while col < cols.len() {
+            if !cols[col] {
+                col += 1;
+                continue;
             }
-        
-            fn color(&self) -> Color {
-                self.color.clone()
+            let start = col;
+            while col < cols.len() && cols[col] {
+                col += 1;
             }
-        
-            fn rotate(&mut self) {
-                let mut new_positions = vec![];
-        
-                let s: f32 = std::f32::consts::FRAC_PI_2.sin();
-                let c: f32 = std::f32::consts::FRAC_PI_2.cos();
-        
-                // We can unwrap as the first position exist
-                let first_pos = self.positions.first().unwrap();
-                let cx = first_pos.x;
-                let cy = first_pos.y;
-        
-                for position in self.positions.iter() {
-                    let trans_x = position.x - cx;
-                    let trans_y = position.y - cy;
-        
-                    let xnew = trans_x * c - trans_y * s;
-                    let ynew = trans_x * s + trans_y * c;
-        
-                    new_positions.push(Vec3::new(xnew + cx, ynew + cy, position.z));
+            let (start, row, length) = (start as i32, row as i32, (col - start) as i32);
+            rectangles.push(quote! {
+                shape.append(&mut crate::piece::piece_builder::PieceBuilder::new_horizontal_rectangle(
+                    #start, #row, #length,
+                ));
+            });
+        }
+    }
+
+    Ok(quote! {
+        impl #name {
+            pub fn new(start_x: i32, start_y: i32) -> Self {
+                #name {
+                    anchor: crate::piece::grid::GridPos::new(start_x, start_y),
                 }
-        
-                self.positions = new_positions;
             }
-        
-            fn move_it(&mut self, cursor: &Res<crate::cursor::Cursor>) {
-                let first_pos = self.positions.first_mut().unwrap();
-        
-                let delta_x = -first_pos.x + cursor.current_pos.x;
-                let delta_y = -first_pos.y + cursor.current_pos.y;
-        
-                first_pos.x = cursor.current_pos.x;
-                first_pos.y = cursor.current_pos.y;
-        
-                for pos in self.positions.iter_mut().skip(1) {
-                    pos.x = pos.x + delta_x;
-                    pos.y = pos.y + delta_y;
-                }
+        }
+
+        impl crate::piece::Piece for #name {
+            fn shape(&self) -> Vec<crate::piece::grid::GridPos> {
+                let mut shape = vec![];
+                #(#rectangles)*
+                shape
             }
 
-            fn snap(&mut self) {
-                for position in self.positions.iter_mut() {
-                    // Here we remove the modulo of a SQUARE height to map to a board position.
-                    let current_x_mod = (position.x as i32) % crate::SQUARE_WIDTH;
-                    let current_y_mod = (position.y as i32) % crate::SQUARE_WIDTH;
-                    let half_width = crate::SQUARE_WIDTH / 2;
-                    if current_x_mod > half_width {
-                        position.x = (position.x as i32 - current_x_mod + crate::SQUARE_WIDTH) as f32;
-                    } else {
-                        position.x = (position.x as i32 - current_x_mod) as f32;
-                    }
-        
-                    if current_y_mod > half_width {
-                        position.y = (position.y as i32 - current_y_mod + crate::SQUARE_WIDTH) as f32;
-                    } else {
-                        position.y = (position.y as i32 - current_y_mod) as f32;
-                    }
-                }
+            fn color(&self) -> bevy::prelude::Color {
+                bevy::prelude::Color::rgba_u8(#red, #green, #blue, #alpha)
             }
-        
-            fn set_moving(&mut self, moving: bool) {
-                self.moving = moving;
+
+            fn anchor(&self) -> crate::piece::grid::GridPos {
+                self.anchor
             }
-        
-            fn is_moving(&self) -> bool {
-                self.moving
+        }
+    })
+}
+
+// The `shape` and `color` of `#[piece(...)]`
+fn piece_attribute(input: &DeriveInput) -> Result<(syn::LitStr, syn::LitStr), Error> {
+    let attribute = input
+        .attrs
+        .iter()
+        .find(|attribute| attribute.path.is_ident("piece"))
+        .ok_or_else(|| {
+            Error::new_spanned(
+                &input.ident,
+                "missing #[piece(shape = \"...\", color = \"#rrggbb\")] attribute",
+            )
+        })?;
+
+    let list = match attribute.parse_meta()? {
+        Meta::List(list) => list,
+        meta => {
+            return Err(Error::new_spanned(
+                meta,
+                "expected #[piece(shape = \"...\", color = \"...\")]",
+            ))
+        }
+    };
+    let (mut shape, mut color) = (None, None);
+    for nested in list.nested.iter() {
+        match nested {
+            NestedMeta::Meta(Meta::NameValue(name_value)) => match &name_value.lit {
+                Lit::Str(value) if name_value.path.is_ident("shape") => shape = Some(value.clone()),
+                Lit::Str(value) if name_value.path.is_ident("color") => color = Some(value.clone()),
+                _ => return Err(Error::new_spanned(name_value, "unknown piece attribute")),
+            },
+            _ => return Err(Error::new_spanned(nested, "unknown piece attribute")),
+        }
+    }
+
+    match (shape, color) {
+        (Some(shape), Some(color)) => Ok((shape, color)),
+        (None, _) => Err(Error::new_spanned(list, "missing piece shape")),
+        (_, None) => Err(Error::new_spanned(list, "missing piece color")),
+    }
+}
+
+// Squares of each row, the last line of the shape being row 0
+fn parse_shape(shape: &str) -> Result<Vec<Vec<bool>>, String> {
+    let mut rows = vec![];
+    for line in shape.lines().rev() {
+        let mut cols = vec![];
+        for c in line.trim_end().chars() {
+            match c {
+                'X' => cols.push(true),
+                '.' | ' ' => cols.push(false),
+                _ => return Err(format!("unknown piece square '{}'", c)),
             }
         }
+        rows.push(cols);
+    }
+
+    let cells: Vec<(usize, usize)> = rows
+        .iter()
+        .enumerate()
+        .flat_map(|(row, cols)| {
+            cols.iter()
+                .enumerate()
+                .filter(|(_, filled)| **filled)
+                .map(move |(col, _)| (col, row))
+        })
+        .collect();
+    if cells.is_empty() {
+        return Err("the piece has no square".to_string());
+    }
+
+    // Every square must be reached from the first one, through the sides
+    let mut reached = vec![cells[0]];
+    let mut i = 0;
+    while i < reached.len() {
+        let (col, row) = reached[i];
+        for cell in cells.iter() {
+            let touches = (cell.0 == col && (cell.1 + 1 == row || row + 1 == cell.1))
+                || (cell.1 == row && (cell.0 + 1 == col || col + 1 == cell.0));
+            if touches && !reached.contains(cell) {
+                reached.push(*cell);
+            }
+        }
+        i += 1;
+    }
+    if reached.len() != cells.len() {
+        return Err("the squares of the piece must be connected".to_string());
+    }
+    Ok(rows)
+}
+
+// `#rrggbb` or `#rrggbbaa`
+fn parse_color(color: &str) -> Result<(u8, u8, u8, u8), String> {
+    let hex = color.trim_start_matches('#');
+    let component = |i: usize| {
+        hex.get(i..i + 2)
+            .and_then(|component| u8::from_str_radix(component, 16).ok())
+            .ok_or_else(|| format!("invalid piece color '{}'", color))
     };
-    TokenStream::from(expanded)
+    match hex.len() {
+        6 => Ok((component(0)?, component(2)?, component(4)?, 255)),
+        8 => Ok((component(0)?, component(2)?, component(4)?, component(6)?)),
+        _ => Err(format!("invalid piece color '{}'", color)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shape_rows_start_from_the_bottom() {
+        assert_eq!(
+            parse_shape("X.\nXX").unwrap(),
+            vec![vec![true, true], vec![true, false]]
+        );
+        // Trailing spaces are ignored, inner ones are holes
+        assert_eq!(
+            parse_shape(".XX  \nXX.").unwrap(),
+            vec![vec![true, true, false], vec![false, true, true]]
+        );
+    }
+
+    #[test]
+    fn empty_shape_is_rejected() {
+        assert_eq!(parse_shape(""), Err("the piece has no square".to_string()));
+        assert_eq!(
+            parse_shape("..\n.."),
+            Err("the piece has no square".to_string())
+        );
+    }
+
+    #[test]
+    fn disconnected_shape_is_rejected() {
+        let error = Err("the squares of the piece must be connected".to_string());
+        assert_eq!(parse_shape("X.X"), error);
+        // Squares touching by a corner only are not connected
+        assert_eq!(parse_shape("X.\n.X"), error);
+    }
+
+    #[test]
+    fn unknown_square_is_rejected() {
+        assert_eq!(
+            parse_shape("XO"),
+            Err("unknown piece square 'O'".to_string())
+        );
+    }
+
+    #[test]
+    fn colors_are_read_with_an_optional_alpha() {
+        assert_eq!(parse_color("#ff1f08"), Ok((255, 31, 8, 255)));
+        assert_eq!(parse_color("#ff1f0880"), Ok((255, 31, 8, 128)));
+    }
+
+    #[test]
+    fn unknown_color_is_rejected() {
+        for color in ["red", "#ff1f0", "#ff1f0g", "#ff1f08a"].iter() {
+            assert_eq!(
+                parse_color(color),
+                Err(format!("invalid piece color '{}'", color))
+            );
+        }
+    }
 }
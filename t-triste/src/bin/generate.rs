@@ -0,0 +1,82 @@
+extern crate t_triste_lib;
+use std::{env, process};
+
+use t_triste_lib::generator::{generate, GeneratorConfig};
+
+const USAGE: &str = "usage: generate <width>x<height> <min>-<max> <seed> [--unique] <level.ron>";
+
+// Generates a level and writes it, the board is cut in pieces of min to max squares:
+// cargo run --bin generate -- 6x4 3-5 42 --unique t-triste-lib/levels/02_generated.ron
+// The game only plays the files listed in `level::BUILTIN_LEVELS`.
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let unique_solution = args.iter().any(|arg| arg == "--unique");
+    let args: Vec<&str> = args
+        .iter()
+        .map(String::as_str)
+        .filter(|arg| *arg != "--unique")
+        .collect();
+    let (config, path) = match parse(&args) {
+        Some((config, path)) => (
+            GeneratorConfig {
+                unique_solution,
+                ..config
+            },
+            path,
+        ),
+        None => {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+
+    let level = match generate(&config) {
+        Ok(level) => level,
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    };
+    if let Err(error) = level.save(path) {
+        eprintln!("{}: {}", path, error);
+        process::exit(1);
+    }
+    println!(
+        "{:?}\t{}\t{} pieces",
+        level.difficulty,
+        path,
+        level.pieces.len()
+    );
+}
+
+fn parse<'a>(args: &[&'a str]) -> Option<(GeneratorConfig, &'a str)> {
+    match args {
+        [size, sizes, seed, path] => {
+            let (width, height) = pair(size, 'x')?;
+            // A single size is a range of one
+            let (min, max) = pair(sizes, '-').or_else(|| {
+                let size = sizes.parse().ok()?;
+                Some((size, size))
+            })?;
+            Some((
+                GeneratorConfig {
+                    width,
+                    height,
+                    piece_sizes: min..=max,
+                    seed: seed.parse().ok()?,
+                    ..Default::default()
+                },
+                path,
+            ))
+        }
+        _ => None,
+    }
+}
+
+// Two numbers around a separator, like 6x4
+fn pair(arg: &str, separator: char) -> Option<(usize, usize)> {
+    let mut parts = arg.splitn(2, separator);
+    let first = parts.next()?.parse().ok()?;
+    let second = parts.next()?.parse().ok()?;
+    Some((first, second))
+}
@@ -0,0 +1,36 @@
+extern crate t_triste_lib;
+use std::{env, process};
+
+use t_triste_lib::{level::Level, rating::rate_level};
+
+// Rates the level files given as arguments, easiest first:
+// cargo run --bin rate -- t-triste-lib/levels/*.ron
+fn main() {
+    let mut rated = vec![];
+    for path in env::args().skip(1) {
+        match Level::load(&path) {
+            Ok(level) => rated.push((path, rate_level(&level))),
+            Err(error) => {
+                eprintln!("{}: {}", path, error);
+                process::exit(1);
+            }
+        }
+    }
+    if rated.is_empty() {
+        eprintln!("usage: rate <level.ron>...");
+        process::exit(1);
+    }
+
+    rated.sort_by(|(_, a), (_, b)| a.score.partial_cmp(&b.score).unwrap());
+    for (path, rating) in rated {
+        println!(
+            "{:6.2} {:?}\t{}\t{} pieces, {} solutions, {} search nodes",
+            rating.score,
+            rating.difficulty(),
+            path,
+            rating.pieces,
+            rating.solutions,
+            rating.search_nodes
+        );
+    }
+}